@@ -0,0 +1,42 @@
+/*
+MIT License
+
+Copyright (c) 2021 Crispin Tschirky <ct@fhr.ch>
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+ */
+use scd30pi::i2c::SCD30;
+use std::env;
+use std::process;
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() != 3 || (args[1] != "save" && args[1] != "restore") {
+        eprintln!("usage: {} save|restore <file.toml>", args[0]);
+        process::exit(1);
+    }
+
+    let mut sensor = SCD30::new().unwrap();
+    let config = if args[1] == "save" {
+        sensor.save_config(&args[2]).unwrap()
+    } else {
+        sensor.restore_config(&args[2]).unwrap()
+    };
+    println!("{}", config.to_toml());
+}
@@ -0,0 +1,11 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use scd30pi::i2c::{decode_measure_value_to_u32, decode_measurement, decode_word};
+
+fuzz_target!(|data: &[u8]| {
+    let _ = decode_word(data);
+    let _ = decode_measure_value_to_u32(data);
+    if let Ok((co2, temperature, humidity)) = decode_measurement(data) {
+        assert!(co2.is_finite() && temperature.is_finite() && humidity.is_finite());
+    }
+});
@@ -0,0 +1,13 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use scd30pi::i2c::analyzer::decode_capture;
+use scd30pi::i2c::config::SensorConfig;
+use scd30pi::i2c::record::parse_recording;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(text) = std::str::from_utf8(data) {
+        let _ = decode_capture(text, 0x61);
+        let _ = parse_recording(text);
+        let _ = SensorConfig::from_toml(text);
+    }
+});
@@ -0,0 +1,269 @@
+/*
+MIT License
+
+Copyright (c) 2021 Crispin Tschirky <ct@fhr.ch>
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+ */
+
+//! Classification of the CO2 concentration into air quality categories.
+//!
+//! A [`Scheme`] divides the concentration into bands, either absolute or as excess over the
+//! outdoor concentration. The common standards are built in, custom bands can be defined. Every
+//! band maps to a traffic light [`Level`].
+//!
+//! ```
+//! use scd30pi::air_quality::{Level, Scheme};
+//!
+//! let scheme = Scheme::uba();
+//! let category = scheme.classify(1350.0);
+//! assert_eq!("conspicuous", category.label);
+//! assert_eq!(Level::Yellow, category.level);
+//! ```
+
+use crate::i2c::{Error, Measurement, Transport, SCD30};
+use std::fmt;
+
+/// Outdoor CO2 concentration in ppm assumed by the built-in schemes
+pub const DEFAULT_OUTDOOR_CO2: f32 = 400.0;
+
+/// Traffic light level of a category.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Green,
+    Yellow,
+    Red,
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Level::Green => write!(f, "green"),
+            Level::Yellow => write!(f, "yellow"),
+            Level::Red => write!(f, "red"),
+        }
+    }
+}
+
+/// A range of the concentration forming a category.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Band {
+    /// name of the category
+    pub label: String,
+    /// highest concentration in ppm of the band, `None` for the last band
+    pub upper: Option<f32>,
+    /// traffic light level
+    pub level: Level,
+}
+
+impl Band {
+    /// Creates a band up to and including the given concentration
+    pub fn up_to(label: &str, upper: f32, level: Level) -> Band {
+        Band {
+            label: label.to_string(),
+            upper: Some(upper),
+            level,
+        }
+    }
+
+    /// Creates the last band without upper limit
+    pub fn above(label: &str, level: Level) -> Band {
+        Band {
+            label: label.to_string(),
+            upper: None,
+            level,
+        }
+    }
+}
+
+/// Category a concentration falls into.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Category<'a> {
+    /// name of the category
+    pub label: &'a str,
+    /// position of the band, 0 is the best
+    pub index: usize,
+    /// traffic light level
+    pub level: Level,
+    /// concentration in ppm compared with the bands, the excess over outdoor for relative schemes
+    pub value: f32,
+}
+
+/// A measurement with its category.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClassifiedMeasurement<'a> {
+    pub measurement: Measurement,
+    pub category: Category<'a>,
+}
+
+/// Bands of a classification standard.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Scheme {
+    /// name of the scheme
+    name: String,
+    /// outdoor concentration in ppm for schemes based on the excess over outdoor
+    outdoor: Option<f32>,
+    /// bands with ascending limits, the last one without limit
+    bands: Vec<Band>,
+}
+
+impl Scheme {
+    /// Creates a scheme from custom bands. With an outdoor concentration the limits are the excess
+    /// over outdoor, otherwise absolute. The limits must ascend and only the last band may be
+    /// unlimited.
+    pub fn custom(name: &str, outdoor: Option<f32>, bands: Vec<Band>) -> Result<Scheme, Error> {
+        let (last, limited) = match bands.split_last() {
+            Some(split) => split,
+            None => return Err(Error::Config("no bands defined".to_string())),
+        };
+        if last.upper.is_some() {
+            return Err(Error::Config(format!(
+                "last band {} must not have an upper limit",
+                last.label
+            )));
+        }
+        let mut previous = f32::NEG_INFINITY;
+        for band in limited {
+            match band.upper {
+                Some(upper) if upper > previous => previous = upper,
+                _ => {
+                    return Err(Error::Config(format!(
+                        "band {} must have an upper limit above the previous one",
+                        band.label
+                    )))
+                }
+            }
+        }
+        Ok(Scheme {
+            name: name.to_string(),
+            outdoor,
+            bands,
+        })
+    }
+
+    /// Indoor air classes IDA 1 to 4 of EN 13779 by the excess over outdoor
+    pub fn en13779(outdoor: f32) -> Scheme {
+        Scheme {
+            name: "EN 13779".to_string(),
+            outdoor: Some(outdoor),
+            bands: vec![
+                Band::up_to("IDA 1", 400.0, Level::Green),
+                Band::up_to("IDA 2", 600.0, Level::Green),
+                Band::up_to("IDA 3", 1000.0, Level::Yellow),
+                Band::above("IDA 4", Level::Red),
+            ],
+        }
+    }
+
+    /// Indoor environment categories I to IV of EN 16798-1 by the excess over outdoor
+    pub fn en16798(outdoor: f32) -> Scheme {
+        Scheme {
+            name: "EN 16798-1".to_string(),
+            outdoor: Some(outdoor),
+            bands: vec![
+                Band::up_to("I", 550.0, Level::Green),
+                Band::up_to("II", 800.0, Level::Green),
+                Band::up_to("III", 1350.0, Level::Yellow),
+                Band::above("IV", Level::Red),
+            ],
+        }
+    }
+
+    /// Guideline of the German Federal Environment Agency (UBA) of 2008 by the absolute
+    /// concentration
+    pub fn uba() -> Scheme {
+        Scheme {
+            name: "UBA".to_string(),
+            outdoor: None,
+            bands: vec![
+                Band::up_to("harmless", 1000.0, Level::Green),
+                Band::up_to("conspicuous", 2000.0, Level::Yellow),
+                Band::above("unacceptable", Level::Red),
+            ],
+        }
+    }
+
+    /// ASHRAE 62.1 rule of thumb: acceptable up to 700 ppm above outdoor
+    pub fn ashrae(outdoor: f32) -> Scheme {
+        Scheme {
+            name: "ASHRAE".to_string(),
+            outdoor: Some(outdoor),
+            bands: vec![
+                Band::up_to("acceptable", 700.0, Level::Green),
+                Band::above("unacceptable", Level::Red),
+            ],
+        }
+    }
+
+    /// Gets the name of the scheme
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Gets the outdoor concentration of a relative scheme
+    pub fn outdoor(&self) -> Option<f32> {
+        self.outdoor
+    }
+
+    /// Gets the bands, best first
+    pub fn bands(&self) -> &[Band] {
+        &self.bands
+    }
+
+    /// Gets the category of the CO2 concentration in ppm
+    pub fn classify(&self, co2: f32) -> Category<'_> {
+        let value = co2 - self.outdoor.unwrap_or(0.0);
+        let index = self
+            .bands
+            .iter()
+            .position(|band| band.upper.map_or(true, |upper| value <= upper))
+            .unwrap_or(self.bands.len() - 1);
+        let band = &self.bands[index];
+        Category {
+            label: &band.label,
+            index,
+            level: band.level,
+            value,
+        }
+    }
+
+    /// Gets the measurement along with the category of its CO2 concentration
+    pub fn classify_measurement(&self, measurement: &Measurement) -> ClassifiedMeasurement<'_> {
+        ClassifiedMeasurement {
+            measurement: *measurement,
+            category: self.classify(measurement.co2),
+        }
+    }
+}
+
+impl<T: Transport> SCD30<T> {
+    /// Gets the measurement along with its category in the given scheme, see
+    /// [`SCD30::measurement`].
+    pub fn classified_measurement<'a>(
+        &mut self,
+        scheme: &'a Scheme,
+    ) -> Result<Option<ClassifiedMeasurement<'a>>, Error> {
+        Ok(self
+            .measurement()?
+            .map(|measurement| scheme.classify_measurement(&measurement)))
+    }
+}
+
+#[cfg(test)]
+mod tests;
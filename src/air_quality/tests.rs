@@ -0,0 +1,140 @@
+/*
+MIT License
+
+Copyright (c) 2021 Crispin Tschirky <ct@fhr.ch>
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+ */
+
+use crate::air_quality::{Band, Level, Scheme, DEFAULT_OUTDOOR_CO2};
+use crate::i2c::sim::testing::simulated_sensor;
+use crate::i2c::Error;
+use std::time::Duration;
+
+fn labels(scheme: &Scheme, values: &[f32]) -> Vec<String> {
+    values
+        .iter()
+        .map(|co2| scheme.classify(*co2).label.to_string())
+        .collect()
+}
+
+#[test]
+fn test_en13779() {
+    let scheme = Scheme::en13779(DEFAULT_OUTDOOR_CO2);
+    assert_eq!(
+        vec!["IDA 1", "IDA 1", "IDA 2", "IDA 3", "IDA 4"],
+        labels(&scheme, &[420.0, 800.0, 801.0, 1400.0, 1401.0])
+    );
+    let category = scheme.classify(1200.0);
+    assert_eq!(2, category.index);
+    assert_eq!(800.0, category.value);
+    assert_eq!(Level::Yellow, category.level);
+}
+
+#[test]
+fn test_en16798() {
+    let scheme = Scheme::en16798(420.0);
+    assert_eq!(
+        vec!["I", "II", "III", "IV"],
+        labels(&scheme, &[970.0, 1220.0, 1770.0, 1771.0])
+    );
+    assert_eq!(Some(420.0), scheme.outdoor());
+}
+
+#[test]
+fn test_uba() {
+    let scheme = Scheme::uba();
+    assert_eq!(
+        vec!["harmless", "conspicuous", "unacceptable"],
+        labels(&scheme, &[1000.0, 2000.0, 2001.0])
+    );
+    assert_eq!(Level::Red, scheme.classify(2500.0).level);
+    assert_eq!(None, scheme.outdoor());
+}
+
+#[test]
+fn test_ashrae() {
+    let scheme = Scheme::ashrae(DEFAULT_OUTDOOR_CO2);
+    assert_eq!(Level::Green, scheme.classify(1100.0).level);
+    assert_eq!(Level::Red, scheme.classify(1101.0).level);
+    assert_eq!("ASHRAE", scheme.name());
+}
+
+#[test]
+fn test_below_outdoor() {
+    let scheme = Scheme::en13779(DEFAULT_OUTDOOR_CO2);
+    let category = scheme.classify(380.0);
+    assert_eq!("IDA 1", category.label);
+    assert_eq!(-20.0, category.value);
+}
+
+#[test]
+fn test_custom_scheme() {
+    let scheme = Scheme::custom(
+        "classroom",
+        None,
+        vec![
+            Band::up_to("fresh", 800.0, Level::Green),
+            Band::up_to("open window", 1400.0, Level::Yellow),
+            Band::above("break", Level::Red),
+        ],
+    )
+    .unwrap();
+    assert_eq!(
+        vec!["fresh", "open window", "break"],
+        labels(&scheme, &[650.0, 1200.0, 1600.0])
+    );
+    assert_eq!(3, scheme.bands().len());
+}
+
+#[test]
+fn test_invalid_custom_schemes() {
+    let invalid = vec![
+        vec![],
+        vec![Band::up_to("a", 800.0, Level::Green)],
+        vec![
+            Band::up_to("a", 800.0, Level::Green),
+            Band::up_to("b", 700.0, Level::Yellow),
+            Band::above("c", Level::Red),
+        ],
+        vec![Band::above("a", Level::Green), Band::above("b", Level::Red)],
+    ];
+    for bands in invalid {
+        match Scheme::custom("invalid", None, bands) {
+            Err(Error::Config(_)) => {}
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+}
+
+#[test]
+fn test_classified_measurement() {
+    let (mut sensor, sim, clock) = simulated_sensor();
+    let scheme = Scheme::uba();
+
+    assert_eq!(None, sensor.classified_measurement(&scheme).unwrap());
+    sim.set_environment(1450.0, 21.0, 45.0);
+    sensor.start().unwrap();
+    clock.advance(Duration::from_secs(2));
+
+    let classified = sensor.classified_measurement(&scheme).unwrap().unwrap();
+    assert_eq!(1450.0, classified.measurement.co2);
+    assert_eq!("conspicuous", classified.category.label);
+    assert_eq!(Level::Yellow, classified.category.level);
+}
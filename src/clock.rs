@@ -0,0 +1,118 @@
+/*
+MIT License
+
+Copyright (c) 2021 Crispin Tschirky <ct@fhr.ch>
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+ */
+
+//! Time source and delays used by the driver.
+//!
+//! The driver never asks the operating system for the time directly but goes through a [`Clock`].
+//! By default the [`StdClock`] is used. Tests inject a [`ManualClock`] which only advances when told
+//! to, so caching, timeouts and retry delays can be checked without waiting.
+
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+/// Source of the current time and a way to wait.
+pub trait Clock: Send + Sync {
+    /// Gets the current time.
+    fn now(&self) -> Instant;
+
+    /// Blocks for the given duration.
+    fn sleep(&self, duration: Duration);
+
+    /// Gets the current wall clock time, e.g. for timestamps in logs.
+    fn system_time(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+/// Clock based on the system monotonic clock and `thread::sleep`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StdClock;
+
+impl Clock for StdClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        thread::sleep(duration);
+    }
+}
+
+/// Clock which only advances when told to. Sleeping advances the clock instead of blocking. Clones
+/// share the same time.
+#[derive(Debug, Clone)]
+pub struct ManualClock {
+    /// monotonic and wall clock time
+    now: Arc<Mutex<(Instant, SystemTime)>>,
+}
+
+impl Default for ManualClock {
+    fn default() -> Self {
+        ManualClock::new()
+    }
+}
+
+impl ManualClock {
+    /// Creates a clock starting at the current time
+    pub fn new() -> ManualClock {
+        ManualClock {
+            now: Arc::new(Mutex::new((Instant::now(), SystemTime::now()))),
+        }
+    }
+
+    /// Advances the clock by the given duration.
+    pub fn advance(&self, duration: Duration) {
+        let mut now = self.now.lock().unwrap_or_else(|e| e.into_inner());
+        now.0 += duration;
+        now.1 += duration;
+    }
+
+    /// Sets the wall clock time, the monotonic time is not changed.
+    pub fn set_system_time(&self, time: SystemTime) {
+        self.now.lock().unwrap_or_else(|e| e.into_inner()).1 = time;
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Instant {
+        self.now.lock().unwrap_or_else(|e| e.into_inner()).0
+    }
+
+    fn sleep(&self, duration: Duration) {
+        self.advance(duration);
+    }
+
+    fn system_time(&self) -> SystemTime {
+        self.now.lock().unwrap_or_else(|e| e.into_inner()).1
+    }
+}
+
+/// Gets the default clock.
+pub fn default_clock() -> Arc<dyn Clock> {
+    Arc::new(StdClock)
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,64 @@
+/*
+MIT License
+
+Copyright (c) 2021 Crispin Tschirky <ct@fhr.ch>
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+ */
+
+use crate::clock::{Clock, ManualClock};
+use std::time::{Duration, UNIX_EPOCH};
+
+#[test]
+fn test_manual_clock_advance() {
+    let clock = ManualClock::new();
+    let start = clock.now();
+    clock.advance(Duration::from_secs(5));
+    assert_eq!(Duration::from_secs(5), clock.now() - start);
+}
+
+#[test]
+fn test_manual_clock_sleep_advances() {
+    let clock = ManualClock::new();
+    let start = clock.now();
+    clock.sleep(Duration::from_secs(3600));
+    assert_eq!(Duration::from_secs(3600), clock.now() - start);
+}
+
+#[test]
+fn test_manual_clock_clones_share_time() {
+    let clock = ManualClock::new();
+    let other = clock.clone();
+    let start = clock.now();
+    other.advance(Duration::from_millis(250));
+    assert_eq!(Duration::from_millis(250), clock.now() - start);
+}
+
+#[test]
+fn test_manual_clock_system_time() {
+    let clock = ManualClock::new();
+    let start = clock.now();
+    clock.set_system_time(UNIX_EPOCH + Duration::from_secs(1_600_000_000));
+    assert_eq!(start, clock.now());
+    clock.advance(Duration::from_secs(5));
+    assert_eq!(
+        UNIX_EPOCH + Duration::from_secs(1_600_000_005),
+        clock.system_time()
+    );
+}
@@ -0,0 +1,356 @@
+/*
+MIT License
+
+Copyright (c) 2021 Crispin Tschirky <ct@fhr.ch>
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+ */
+
+//! Decodes SCD30 traffic captured with a logic analyzer.
+//!
+//! The input is the annotation export of the sigrok I²C protocol decoder, either the CSV export of
+//! PulseView or the output of `sigrok-cli -P i2c`. Each line carrying one of the annotations
+//! `Start`, `Start repeat`, `Stop`, `ACK`, `NACK`, `Address read: 61`, `Address write: 61`,
+//! `Data read: 02` or `Data write: 02` is used, all other lines are ignored. A leading number on a
+//! line is taken as sample number.
+//!
+//! The I²C frames are then decoded to sensor operations: command names, arguments, response words
+//! and measurement values, each with the result of the CRC check.
+//!
+//! ```
+//! use scd30pi::i2c::analyzer::decode_capture;
+//!
+//! let capture = "\
+//! 100,i2c-1: Start
+//! 120,i2c-1: Address write: 61
+//! 200,i2c-1: Data write: 46
+//! 280,i2c-1: Data write: 00
+//! 360,i2c-1: Data write: 00
+//! 440,i2c-1: Data write: 05
+//! 520,i2c-1: Data write: 74
+//! 600,i2c-1: Stop
+//! ";
+//! let operations = decode_capture(capture, 0x61);
+//! assert_eq!("measurement interval", operations[0].name);
+//! assert_eq!(Some(5), operations[0].argument);
+//! ```
+
+use crate::i2c::{calculate_crc8, command_name, CMD_GET_MEASUREMENT, CMD_SET_TEMPERATURE_OFFSET};
+use std::fmt;
+
+/// A transfer on the I²C bus between a (repeated) start and the next start or stop condition.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    /// sample number of the start condition if known
+    pub sample: Option<u64>,
+    /// 7 bit slave address
+    pub address: u16,
+    /// true for a read, false for a write transfer
+    pub read: bool,
+    /// transferred data bytes
+    pub data: Vec<u8>,
+    /// true if the address was not acknowledged
+    pub nack: bool,
+}
+
+/// A decoded response of the sensor.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Response {
+    /// A single word
+    Word { value: u16, crc_ok: bool },
+    /// CO2 in ppm, temperature in °C and humidity in %
+    Measurement {
+        co2: f32,
+        temperature: f32,
+        humidity: f32,
+        crc_ok: bool,
+    },
+    /// Data not matching the expected response
+    Raw(Vec<u8>),
+}
+
+/// A decoded sensor operation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Operation {
+    /// sample number of the command if known
+    pub sample: Option<u64>,
+    /// command word
+    pub command: u16,
+    /// name of the command, "unknown" if not known
+    pub name: &'static str,
+    /// argument word if one was sent
+    pub argument: Option<u16>,
+    /// result of the CRC check of the argument
+    pub argument_crc_ok: Option<bool>,
+    /// response read after the command
+    pub response: Option<Response>,
+    /// true if the sensor did not acknowledge the command or the read
+    pub nack: bool,
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(sample) = self.sample {
+            write!(f, "@{} ", sample)?;
+        }
+        write!(f, "{:#06x} {}", self.command, self.name)?;
+        if let Some(argument) = self.argument {
+            if self.command == CMD_SET_TEMPERATURE_OFFSET {
+                write!(f, " = {} ({:.2} °C)", argument, argument as f32 / 100.0)?;
+            } else {
+                write!(f, " = {}", argument)?;
+            }
+            write!(f, " [{}]", crc_text(self.argument_crc_ok.unwrap_or(false)))?;
+        }
+        match &self.response {
+            Some(Response::Word { value, crc_ok }) => {
+                write!(f, " -> {} [{}]", value, crc_text(*crc_ok))?
+            }
+            Some(Response::Measurement {
+                co2,
+                temperature,
+                humidity,
+                crc_ok,
+            }) => write!(
+                f,
+                " -> co2 = {:.0} ppm, temp = {:.2} °C, humidity = {:.0} % [{}]",
+                co2,
+                temperature,
+                humidity,
+                crc_text(*crc_ok)
+            )?,
+            Some(Response::Raw(data)) => write!(f, " -> raw {:02x?}", data)?,
+            None => {}
+        }
+        if self.nack {
+            write!(f, " NACK")?;
+        }
+        Ok(())
+    }
+}
+
+fn crc_text(crc_ok: bool) -> &'static str {
+    if crc_ok {
+        "crc ok"
+    } else {
+        "crc FAIL"
+    }
+}
+
+/// Annotation of the sigrok I²C decoder
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Annotation {
+    Start,
+    Stop,
+    Ack,
+    Nack,
+    Address { address: u16, read: bool },
+    Data(u8),
+}
+
+/// Finds the annotation in a line of the export.
+fn parse_annotation(line: &str) -> Option<Annotation> {
+    let hex_after = |marker: &str| -> Option<u16> {
+        let start = line.find(marker)? + marker.len();
+        let hex: String = line[start..]
+            .trim_start()
+            .chars()
+            .take_while(|c| c.is_ascii_hexdigit())
+            .collect();
+        u16::from_str_radix(&hex, 16).ok()
+    };
+    if let Some(address) = hex_after("Address write:") {
+        return Some(Annotation::Address {
+            address,
+            read: false,
+        });
+    }
+    if let Some(address) = hex_after("Address read:") {
+        return Some(Annotation::Address {
+            address,
+            read: true,
+        });
+    }
+    if let Some(data) = hex_after("Data write:").or_else(|| hex_after("Data read:")) {
+        return if data <= 0xff {
+            Some(Annotation::Data(data as u8))
+        } else {
+            None
+        };
+    }
+    let words: Vec<&str> = line
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .collect();
+    if words.contains(&"NACK") {
+        Some(Annotation::Nack)
+    } else if words.contains(&"ACK") {
+        Some(Annotation::Ack)
+    } else if words.contains(&"Start") {
+        Some(Annotation::Start)
+    } else if words.contains(&"Stop") {
+        Some(Annotation::Stop)
+    } else {
+        None
+    }
+}
+
+/// Gets the sample number at the start of a line
+fn parse_sample(line: &str) -> Option<u64> {
+    let digits: String = line
+        .trim_start_matches(|c: char| c == '"' || c.is_whitespace())
+        .chars()
+        .take_while(|c| c.is_ascii_digit())
+        .collect();
+    digits.parse().ok()
+}
+
+/// Splits an annotation export into I²C frames.
+pub fn parse_frames(content: &str) -> Vec<Frame> {
+    let mut frames = Vec::new();
+    let mut current: Option<Frame> = None;
+    let mut start_sample = None;
+    let mut after_address = false;
+
+    for line in content.lines() {
+        let annotation = match parse_annotation(line) {
+            Some(annotation) => annotation,
+            None => continue,
+        };
+        match annotation {
+            Annotation::Start => {
+                frames.extend(current.take());
+                start_sample = parse_sample(line);
+            }
+            Annotation::Stop => frames.extend(current.take()),
+            Annotation::Address { address, read } => {
+                frames.extend(current.take());
+                current = Some(Frame {
+                    sample: start_sample.take().or_else(|| parse_sample(line)),
+                    address,
+                    read,
+                    data: Vec::new(),
+                    nack: false,
+                });
+                after_address = true;
+                continue;
+            }
+            Annotation::Data(byte) => {
+                if let Some(frame) = current.as_mut() {
+                    frame.data.push(byte);
+                }
+            }
+            Annotation::Nack => {
+                if let Some(frame) = current.as_mut() {
+                    if after_address {
+                        frame.nack = true;
+                    }
+                }
+            }
+            Annotation::Ack => {}
+        }
+        after_address = false;
+    }
+    frames.extend(current.take());
+    frames
+}
+
+/// Decodes the frames addressed to the sensor at the given address to operations.
+pub fn decode_frames(frames: &[Frame], address: u16) -> Vec<Operation> {
+    let mut operations: Vec<Operation> = Vec::new();
+    for frame in frames.iter().filter(|f| f.address == address) {
+        if frame.read {
+            match operations.last_mut() {
+                Some(op) if op.response.is_none() && op.argument.is_none() => {
+                    op.nack |= frame.nack;
+                    if !frame.nack {
+                        op.response = Some(decode_response(op.command, &frame.data));
+                    }
+                }
+                _ => operations.push(Operation {
+                    sample: frame.sample,
+                    command: 0,
+                    name: "read without command",
+                    argument: None,
+                    argument_crc_ok: None,
+                    response: Some(Response::Raw(frame.data.clone())),
+                    nack: frame.nack,
+                }),
+            }
+            continue;
+        }
+        let (command, name) = if frame.data.len() >= 2 {
+            let command = ((frame.data[0] as u16) << 8) | frame.data[1] as u16;
+            (command, command_name(command).unwrap_or("unknown"))
+        } else {
+            (0, "incomplete command")
+        };
+        let (argument, argument_crc_ok) = if frame.data.len() >= 5 {
+            (
+                Some(((frame.data[2] as u16) << 8) | frame.data[3] as u16),
+                Some(calculate_crc8(&frame.data[2..5]) == 0),
+            )
+        } else {
+            (None, None)
+        };
+        operations.push(Operation {
+            sample: frame.sample,
+            command,
+            name,
+            argument,
+            argument_crc_ok,
+            response: None,
+            nack: frame.nack,
+        });
+    }
+    operations
+}
+
+/// Decodes an annotation export to the operations of the sensor at the given address.
+pub fn decode_capture(content: &str, address: u16) -> Vec<Operation> {
+    decode_frames(&parse_frames(content), address)
+}
+
+/// Decodes the response to the given command
+fn decode_response(command: u16, data: &[u8]) -> Response {
+    match (command, data.len()) {
+        (CMD_GET_MEASUREMENT, 18) => {
+            let crc_ok = data.chunks(3).all(|word| calculate_crc8(word) == 0);
+            let value = |offset: usize| {
+                let d = &data[offset..offset + 6];
+                f32::from_bits(u32::from_be_bytes([d[0], d[1], d[3], d[4]]))
+            };
+            Response::Measurement {
+                co2: value(0),
+                temperature: value(6),
+                humidity: value(12),
+                crc_ok,
+            }
+        }
+        (CMD_GET_MEASUREMENT, _) => Response::Raw(data.to_vec()),
+        (_, 3) => Response::Word {
+            value: ((data[0] as u16) << 8) | data[1] as u16,
+            crc_ok: calculate_crc8(data) == 0,
+        },
+        _ => Response::Raw(data.to_vec()),
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,163 @@
+/*
+MIT License
+
+Copyright (c) 2021 Crispin Tschirky <ct@fhr.ch>
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+ */
+
+use crate::i2c::analyzer::{decode_capture, parse_frames, Response};
+use crate::i2c::{calculate_crc8, prepare_cmd, prepare_cmd_with_args};
+
+/// Builds a sigrok-cli style export of a write followed by an optional read.
+fn capture(address: u8, write: &[u8], read: Option<&[u8]>) -> String {
+    let mut lines = vec![
+        "i2c-1: Start".to_string(),
+        format!("i2c-1: Address write: {:02X}", address),
+        "i2c-1: Write".to_string(),
+        "i2c-1: ACK".to_string(),
+    ];
+    for b in write {
+        lines.push(format!("i2c-1: Data write: {:02X}", b));
+        lines.push("i2c-1: ACK".to_string());
+    }
+    lines.push("i2c-1: Stop".to_string());
+    if let Some(read) = read {
+        lines.push("i2c-1: Start".to_string());
+        lines.push(format!("i2c-1: Address read: {:02X}", address));
+        lines.push("i2c-1: ACK".to_string());
+        for b in read {
+            lines.push(format!("i2c-1: Data read: {:02X}", b));
+            lines.push("i2c-1: ACK".to_string());
+        }
+        lines.push("i2c-1: Stop".to_string());
+    }
+    lines.join("\n") + "\n"
+}
+
+fn encode_value(value: f32) -> Vec<u8> {
+    let b = value.to_bits().to_be_bytes();
+    vec![
+        b[0],
+        b[1],
+        calculate_crc8(&b[0..2]),
+        b[2],
+        b[3],
+        calculate_crc8(&b[2..4]),
+    ]
+}
+
+#[test]
+fn test_decode_command_with_argument() {
+    let ops = decode_capture(
+        &capture(0x61, &prepare_cmd_with_args(0x5403, 150), None),
+        0x61,
+    );
+    assert_eq!(1, ops.len());
+    assert_eq!(0x5403, ops[0].command);
+    assert_eq!("temperature offset", ops[0].name);
+    assert_eq!(Some(150), ops[0].argument);
+    assert_eq!(Some(true), ops[0].argument_crc_ok);
+    assert!(ops[0].to_string().contains("1.50 °C"));
+}
+
+#[test]
+fn test_decode_argument_crc_failure() {
+    let mut buf = prepare_cmd_with_args(0x4600, 5);
+    buf[4] ^= 0x01;
+    let ops = decode_capture(&capture(0x61, &buf, None), 0x61);
+    assert_eq!(Some(false), ops[0].argument_crc_ok);
+    assert!(ops[0].to_string().contains("crc FAIL"));
+}
+
+#[test]
+fn test_decode_word_response() {
+    let response = [0x03, 0x42, calculate_crc8(&[0x03, 0x42])];
+    let ops = decode_capture(&capture(0x61, &prepare_cmd(0xd100), Some(&response)), 0x61);
+    assert_eq!("read firmware version", ops[0].name);
+    assert_eq!(
+        Some(Response::Word {
+            value: 0x0342,
+            crc_ok: true
+        }),
+        ops[0].response
+    );
+}
+
+#[test]
+fn test_decode_measurement() {
+    let mut data = encode_value(842.5);
+    data.extend(encode_value(22.25));
+    data.extend(encode_value(40.0));
+    let ops = decode_capture(&capture(0x61, &prepare_cmd(0x0300), Some(&data)), 0x61);
+    assert_eq!(
+        Some(Response::Measurement {
+            co2: 842.5,
+            temperature: 22.25,
+            humidity: 40.0,
+            crc_ok: true
+        }),
+        ops[0].response
+    );
+    data[17] ^= 0xff;
+    let ops = decode_capture(&capture(0x61, &prepare_cmd(0x0300), Some(&data)), 0x61);
+    match ops[0].response {
+        Some(Response::Measurement { crc_ok: false, .. }) => {}
+        ref other => panic!("unexpected response {:?}", other),
+    }
+}
+
+#[test]
+fn test_decode_ignores_other_addresses() {
+    let mut content = capture(0x76, &[0xf7], Some(&[1, 2, 3]));
+    content.push_str(&capture(0x61, &prepare_cmd(0x0104), None));
+    let ops = decode_capture(&content, 0x61);
+    assert_eq!(1, ops.len());
+    assert_eq!("stop continuous measurement", ops[0].name);
+}
+
+#[test]
+fn test_decode_address_nack() {
+    let content = "i2c-1: Start\ni2c-1: Address write: 61\ni2c-1: NACK\ni2c-1: Stop\n";
+    let frames = parse_frames(content);
+    assert_eq!(1, frames.len());
+    assert!(frames[0].nack);
+    let ops = decode_capture(content, 0x61);
+    assert!(ops[0].nack);
+    assert_eq!("incomplete command", ops[0].name);
+}
+
+#[test]
+fn test_parse_pulseview_csv() {
+    let content = r#""Start Sample","End Sample","Class","Description"
+"1200","1210","start","I²C: Address/data: Start"
+"1215","1290","address-write","I²C: Address/data: Address write: 61"
+"1300","1310","ack","I²C: Address/data: ACK"
+"1320","1400","data-write","I²C: Address/data: Data write: 01"
+"1410","1420","ack","I²C: Address/data: ACK"
+"1430","1510","data-write","I²C: Address/data: Data write: 04"
+"1520","1530","stop","I²C: Address/data: Stop"
+"#;
+    let frames = parse_frames(content);
+    assert_eq!(1, frames.len());
+    assert_eq!(Some(1200), frames[0].sample);
+    assert_eq!(vec![0x01, 0x04], frames[0].data);
+    let ops = decode_capture(content, 0x61);
+    assert_eq!("stop continuous measurement", ops[0].name);
+}
@@ -0,0 +1,295 @@
+/*
+MIT License
+
+Copyright (c) 2021 Crispin Tschirky <ct@fhr.ch>
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+ */
+
+//! Readiness tracking of the automatic self calibration (ASC).
+//!
+//! The ASC of the SCD30 assumes the lowest concentration seen is fresh air at 400 ppm. It needs
+//! the sensor to run continuously for seven days and to see fresh air for about an hour every day.
+//! An [`AscTracker`] is fed with the measurements, keeps the daily minimum, uptime and fresh air
+//! time and tells whether the prerequisites are met. Days are counted in periods of 24 hours from
+//! the first measurement.
+//!
+//! ```no_run
+//! use scd30pi::i2c::asc::AscTracker;
+//! use scd30pi::i2c::SCD30;
+//! use std::{thread, time::Duration};
+//!
+//! let mut sensor = SCD30::new().unwrap();
+//! let mut tracker = AscTracker::new();
+//! sensor.start().unwrap();
+//! loop {
+//!     if let Some(measurement) = sensor.measurement().unwrap() {
+//!         tracker.record(&measurement);
+//!     }
+//!     for warning in tracker.check(&mut sensor).unwrap() {
+//!         println!("{}", warning);
+//!     }
+//!     thread::sleep(Duration::from_secs(60));
+//! }
+//! ```
+
+use crate::i2c::{Error, Measurement, Transport, SCD30};
+use std::collections::VecDeque;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// Number of days the ASC needs to find the fresh air baseline
+pub const ASC_REQUIRED_DAYS: usize = 7;
+
+const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Statistics of a single day.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DayStats {
+    /// lowest CO2 concentration in ppm, `None` without stable readings
+    pub min_co2: Option<f32>,
+    /// time covered by measurements
+    pub uptime: Duration,
+    /// time with readings at or below the fresh air threshold
+    pub fresh_air: Duration,
+}
+
+impl DayStats {
+    fn new() -> DayStats {
+        DayStats {
+            min_co2: None,
+            uptime: Duration::from_secs(0),
+            fresh_air: Duration::from_secs(0),
+        }
+    }
+}
+
+/// Whether the ASC prerequisites are met.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AscReadiness {
+    /// number of complete days tracked
+    pub days_tracked: usize,
+    /// number of the most recent consecutive days meeting the prerequisites
+    pub days_met: usize,
+    /// true if the prerequisites were met for the last seven days
+    pub ready: bool,
+}
+
+/// Problems of the ASC setup.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AscWarning {
+    /// ASC is enabled but the room never got fresh air during the tracked days, the readings will
+    /// be calibrated too low. `min_co2` is infinite without stable readings.
+    NoFreshAir { days: usize, min_co2: f32 },
+    /// ASC is enabled but the measurement did not run continuously on the given number of days
+    Interrupted { days: usize },
+}
+
+impl fmt::Display for AscWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AscWarning::NoFreshAir { days, min_co2 } => write!(
+                f,
+                "self calibration enabled but no fresh air in {} days, lowest CO2 {:.0} ppm",
+                days, min_co2
+            ),
+            AscWarning::Interrupted { days } => write!(
+                f,
+                "self calibration enabled but measurement interrupted on {} days",
+                days
+            ),
+        }
+    }
+}
+
+/// Tracks the daily minimum CO2 concentration, uptime and fresh air exposure.
+#[derive(Debug, Clone)]
+pub struct AscTracker {
+    /// highest concentration in ppm considered fresh air
+    fresh_air_threshold: f32,
+    /// fresh air time required per day
+    required_fresh_air: Duration,
+    /// uptime required per day
+    required_uptime: Duration,
+    /// longest time between two measurements still counted as uptime
+    max_gap: Duration,
+    /// start of the first day
+    started: Option<Instant>,
+    /// time of the last measurement
+    last: Option<Instant>,
+    /// number of the current day counted from the start
+    current_day: usize,
+    /// complete days, oldest first, and the current day as last entry
+    days: VecDeque<DayStats>,
+}
+
+impl Default for AscTracker {
+    fn default() -> Self {
+        AscTracker::new()
+    }
+}
+
+impl AscTracker {
+    /// Creates a tracker with a fresh air threshold of 450 ppm, one hour of fresh air and 23 hours
+    /// of uptime per day.
+    pub fn new() -> AscTracker {
+        AscTracker {
+            fresh_air_threshold: 450.0,
+            required_fresh_air: Duration::from_secs(60 * 60),
+            required_uptime: Duration::from_secs(23 * 60 * 60),
+            max_gap: Duration::from_secs(5 * 60),
+            started: None,
+            last: None,
+            current_day: 0,
+            days: VecDeque::new(),
+        }
+    }
+
+    /// Sets the highest concentration in ppm considered fresh air
+    pub fn set_fresh_air_threshold(&mut self, threshold: f32) {
+        self.fresh_air_threshold = threshold;
+    }
+
+    /// Sets the fresh air time required per day
+    pub fn set_required_fresh_air(&mut self, required: Duration) {
+        self.required_fresh_air = required;
+    }
+
+    /// Sets the uptime required per day
+    pub fn set_required_uptime(&mut self, required: Duration) {
+        self.required_uptime = required;
+    }
+
+    /// Sets the longest time between two measurements still counted as uptime, it has to be longer
+    /// than the measure interval.
+    pub fn set_max_gap(&mut self, max_gap: Duration) {
+        self.max_gap = max_gap;
+    }
+
+    /// Records a measurement. The time since the previous measurement is counted as uptime of the
+    /// day of the measurement. Measurements flagged as stabilizing only count as uptime.
+    pub fn record(&mut self, measurement: &Measurement) {
+        let timestamp = measurement.timestamp;
+        let started = *self.started.get_or_insert(timestamp);
+        if self.last.is_some_and(|last| timestamp <= last) {
+            return;
+        }
+        let day = (timestamp.saturating_duration_since(started).as_secs() / DAY.as_secs()) as usize;
+        if self.days.is_empty() {
+            self.days.push_back(DayStats::new());
+        }
+        for _ in self.current_day..day {
+            self.days.push_back(DayStats::new());
+        }
+        while self.days.len() > ASC_REQUIRED_DAYS + 1 {
+            self.days.pop_front();
+        }
+        self.current_day = day;
+
+        let elapsed = self
+            .last
+            .map(|last| timestamp - last)
+            .filter(|elapsed| *elapsed <= self.max_gap);
+        self.last = Some(timestamp);
+        let stats = self.days.back_mut().expect("current day");
+        if let Some(elapsed) = elapsed {
+            stats.uptime += elapsed;
+            if !measurement.stabilizing && measurement.co2 <= self.fresh_air_threshold {
+                stats.fresh_air += elapsed;
+            }
+        }
+        if !measurement.stabilizing {
+            stats.min_co2 = Some(
+                stats
+                    .min_co2
+                    .map_or(measurement.co2, |min| min.min(measurement.co2)),
+            );
+        }
+    }
+
+    /// Gets the complete days of the last week, oldest first
+    pub fn days(&self) -> Vec<DayStats> {
+        let complete = self.days.len().saturating_sub(1);
+        self.days.iter().take(complete).copied().collect()
+    }
+
+    /// Gets the statistics of the current day
+    pub fn current_day(&self) -> Option<DayStats> {
+        self.days.back().copied()
+    }
+
+    /// True if the day meets the prerequisites of the ASC
+    fn meets_prerequisites(&self, day: &DayStats) -> bool {
+        day.uptime >= self.required_uptime && day.fresh_air >= self.required_fresh_air
+    }
+
+    /// Gets whether the ASC prerequisites were met during the last seven complete days
+    pub fn readiness(&self) -> AscReadiness {
+        let days = self.days();
+        let days_met = days
+            .iter()
+            .rev()
+            .take_while(|day| self.meets_prerequisites(day))
+            .count();
+        AscReadiness {
+            days_tracked: days.len(),
+            days_met,
+            ready: days_met >= ASC_REQUIRED_DAYS,
+        }
+    }
+
+    /// Gets the problems of the setup if the ASC is enabled. Nothing is reported before the first
+    /// day is complete.
+    pub fn warnings(&self, asc_enabled: bool) -> Vec<AscWarning> {
+        let days = self.days();
+        let mut warnings = Vec::new();
+        if !asc_enabled || days.is_empty() {
+            return warnings;
+        }
+        let min_co2 = days
+            .iter()
+            .filter_map(|day| day.min_co2)
+            .fold(f32::INFINITY, f32::min);
+        if !days
+            .iter()
+            .any(|day| day.fresh_air > Duration::from_secs(0))
+        {
+            warnings.push(AscWarning::NoFreshAir {
+                days: days.len(),
+                min_co2,
+            });
+        }
+        let interrupted = days
+            .iter()
+            .filter(|day| day.uptime < self.required_uptime)
+            .count();
+        if interrupted > 0 {
+            warnings.push(AscWarning::Interrupted { days: interrupted });
+        }
+        warnings
+    }
+
+    /// Reads whether the ASC of the sensor is enabled and gets the problems of the setup
+    pub fn check<T: Transport>(&self, sensor: &mut SCD30<T>) -> Result<Vec<AscWarning>, Error> {
+        Ok(self.warnings(sensor.read_self_calibration()?))
+    }
+}
+
+#[cfg(test)]
+mod tests;
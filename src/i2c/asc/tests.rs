@@ -0,0 +1,199 @@
+/*
+MIT License
+
+Copyright (c) 2021 Crispin Tschirky <ct@fhr.ch>
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+ */
+
+use crate::clock::Clock;
+use crate::i2c::asc::{AscTracker, AscWarning};
+use crate::i2c::sim::testing::simulated_sensor;
+use crate::i2c::Measurement;
+use std::time::{Duration, Instant};
+
+const MINUTE: u64 = 60;
+const HOUR: u64 = 60 * MINUTE;
+const DAY: u64 = 24 * HOUR;
+
+fn measurement(timestamp: Instant, co2: f32) -> Measurement {
+    Measurement {
+        co2,
+        temperature: 21.0,
+        humidity: 45.0,
+        timestamp,
+        age: Duration::from_secs(0),
+        stabilizing: false,
+    }
+}
+
+/// Feeds one measurement per minute from `from` to `to` seconds after the start
+fn feed<F: Fn(u64) -> f32>(tracker: &mut AscTracker, start: Instant, from: u64, to: u64, co2: F) {
+    for t in (from..to).step_by(MINUTE as usize) {
+        tracker.record(&measurement(start + Duration::from_secs(t), co2(t)));
+    }
+}
+
+/// Office with fresh air from 6:00 to 8:00 and occupancy during the day
+fn office(t: u64) -> f32 {
+    match t % DAY {
+        s if s < 6 * HOUR => 550.0,
+        s if s < 8 * HOUR => 410.0,
+        _ => 900.0,
+    }
+}
+
+#[test]
+fn test_daily_statistics() {
+    let mut tracker = AscTracker::new();
+    let start = Instant::now();
+    feed(&mut tracker, start, 0, DAY + HOUR, office);
+
+    let days = tracker.days();
+    assert_eq!(1, days.len());
+    assert_eq!(Some(410.0), days[0].min_co2);
+    // the first measurement only starts the tracking
+    assert_eq!(Duration::from_secs(DAY - MINUTE), days[0].uptime);
+    assert_eq!(Duration::from_secs(2 * HOUR), days[0].fresh_air);
+
+    let current = tracker.current_day().unwrap();
+    assert_eq!(Duration::from_secs(HOUR), current.uptime);
+}
+
+#[test]
+fn test_ready_after_seven_days() {
+    let mut tracker = AscTracker::new();
+    let start = Instant::now();
+    feed(&mut tracker, start, 0, 7 * DAY, office);
+    let readiness = tracker.readiness();
+    assert_eq!(6, readiness.days_tracked);
+    assert!(!readiness.ready);
+
+    feed(&mut tracker, start, 7 * DAY, 8 * DAY, office);
+    let readiness = tracker.readiness();
+    assert_eq!(7, readiness.days_tracked);
+    assert_eq!(7, readiness.days_met);
+    assert!(readiness.ready);
+    assert!(tracker.warnings(true).is_empty());
+}
+
+#[test]
+fn test_interruption_resets_readiness() {
+    let mut tracker = AscTracker::new();
+    let start = Instant::now();
+    feed(&mut tracker, start, 0, 3 * DAY, office);
+    // power outage of two hours
+    feed(
+        &mut tracker,
+        start,
+        3 * DAY + 2 * HOUR,
+        10 * DAY + HOUR,
+        office,
+    );
+
+    let readiness = tracker.readiness();
+    assert_eq!(7, readiness.days_tracked);
+    assert_eq!(6, readiness.days_met);
+    assert!(!readiness.ready);
+    assert_eq!(
+        vec![AscWarning::Interrupted { days: 1 }],
+        tracker.warnings(true)
+    );
+}
+
+#[test]
+fn test_missing_days_count_as_interrupted() {
+    let mut tracker = AscTracker::new();
+    let start = Instant::now();
+    feed(&mut tracker, start, 0, DAY, office);
+    feed(&mut tracker, start, 3 * DAY, 4 * DAY + HOUR, office);
+
+    let days = tracker.days();
+    assert_eq!(4, days.len());
+    assert_eq!(None, days[1].min_co2);
+    assert_eq!(Duration::from_secs(0), days[2].uptime);
+    assert_eq!(
+        vec![AscWarning::Interrupted { days: 2 }],
+        tracker.warnings(true)
+    );
+}
+
+#[test]
+fn test_no_fresh_air_warning() {
+    let mut tracker = AscTracker::new();
+    let start = Instant::now();
+    feed(&mut tracker, start, 0, 2 * DAY + HOUR, |t| {
+        office(t) + 200.0
+    });
+
+    let readiness = tracker.readiness();
+    assert_eq!(0, readiness.days_met);
+    let warnings = tracker.warnings(true);
+    assert_eq!(
+        vec![AscWarning::NoFreshAir {
+            days: 2,
+            min_co2: 610.0
+        }],
+        warnings
+    );
+    assert_eq!(
+        "self calibration enabled but no fresh air in 2 days, lowest CO2 610 ppm",
+        warnings[0].to_string()
+    );
+    assert!(tracker.warnings(false).is_empty());
+}
+
+#[test]
+fn test_history_limited_to_a_week() {
+    let mut tracker = AscTracker::new();
+    let start = Instant::now();
+    feed(&mut tracker, start, 0, DAY, |t| office(t) + 200.0);
+    feed(&mut tracker, start, DAY, 9 * DAY, office);
+
+    assert_eq!(7, tracker.days().len());
+    assert!(tracker.warnings(true).is_empty());
+}
+
+#[test]
+fn test_stabilizing_readings_only_count_as_uptime() {
+    let mut tracker = AscTracker::new();
+    let start = Instant::now();
+    for minute in 0..10 {
+        let mut m = measurement(start + Duration::from_secs(minute * MINUTE), 400.0);
+        m.stabilizing = true;
+        tracker.record(&m);
+    }
+    let day = tracker.current_day().unwrap();
+    assert_eq!(None, day.min_co2);
+    assert_eq!(Duration::from_secs(9 * MINUTE), day.uptime);
+    assert_eq!(Duration::from_secs(0), day.fresh_air);
+}
+
+#[test]
+fn test_check_reads_self_calibration() {
+    let (mut sensor, _, clock) = simulated_sensor();
+
+    let mut tracker = AscTracker::new();
+    let start = clock.now();
+    feed(&mut tracker, start, 0, DAY + HOUR, |_| 800.0);
+
+    assert!(tracker.check(&mut sensor).unwrap().is_empty());
+    sensor.enable_self_calibration().unwrap();
+    assert_eq!(1, tracker.check(&mut sensor).unwrap().len());
+}
@@ -0,0 +1,241 @@
+/*
+MIT License
+
+Copyright (c) 2021 Crispin Tschirky <ct@fhr.ch>
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+ */
+
+//! Audit log of calibration-affecting calls.
+//!
+//! Once an [`AuditStore`] is set with [`SCD30::set_audit_store`], every call changing the
+//! calibration of the sensor appends an [`AuditEntry`] with the time, the values before and after
+//! the call where the sensor can report them and the note of the operator.
+//!
+//! ```no_run
+//! use scd30pi::i2c::audit::FileAuditStore;
+//! use scd30pi::i2c::SCD30;
+//!
+//! let mut sensor = SCD30::new().unwrap();
+//! sensor.set_audit_store(Box::new(FileAuditStore::open("/var/log/scd30-audit.log").unwrap()));
+//! sensor.set_audit_note(Some("outdoor FRC by jdoe".to_string()));
+//! sensor.set_forced_recalibration(420).unwrap();
+//! ```
+
+use crate::i2c::{Error, Transport, SCD30};
+use log::warn;
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Calibration-affecting calls.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuditAction {
+    ForcedRecalibration,
+    SelfCalibration,
+    TemperatureOffset,
+    AltitudeCompensation,
+    SoftReset,
+}
+
+impl fmt::Display for AuditAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AuditAction::ForcedRecalibration => write!(f, "forced_recalibration"),
+            AuditAction::SelfCalibration => write!(f, "self_calibration"),
+            AuditAction::TemperatureOffset => write!(f, "temperature_offset"),
+            AuditAction::AltitudeCompensation => write!(f, "altitude_compensation"),
+            AuditAction::SoftReset => write!(f, "soft_reset"),
+        }
+    }
+}
+
+/// A single calibration-affecting call.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuditEntry {
+    /// time of the call taken from the clock of the sensor
+    pub timestamp: SystemTime,
+    /// the call
+    pub action: AuditAction,
+    /// value passed to the sensor
+    pub requested: Option<String>,
+    /// value read from the sensor before the call
+    pub before: Option<String>,
+    /// value read from the sensor after the call
+    pub after: Option<String>,
+    /// note of the operator
+    pub note: Option<String>,
+    /// error of the call if it failed
+    pub error: Option<String>,
+}
+
+/// Formats the entry as a single tab separated line: time in seconds since the epoch, action,
+/// requested, before, after, note and error with `-` for missing values.
+impl fmt::Display for AuditEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let time = self
+            .timestamp
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        write!(
+            f,
+            "{}.{:03}\t{}",
+            time.as_secs(),
+            time.subsec_millis(),
+            self.action
+        )?;
+        for value in &[
+            &self.requested,
+            &self.before,
+            &self.after,
+            &self.note,
+            &self.error,
+        ] {
+            match value {
+                Some(value) => write!(f, "\t{}", value.replace(['\t', '\n', '\r'], " "))?,
+                None => write!(f, "\t-")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// An append-only store of audit entries.
+pub trait AuditStore: Send {
+    /// Appends the entry to the store
+    fn append(&mut self, entry: &AuditEntry) -> Result<(), Error>;
+}
+
+/// Keeps the entries in memory. Clones share the same entries, so a handle can be kept to read
+/// them while the sensor owns another.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryAuditStore {
+    entries: Arc<Mutex<Vec<AuditEntry>>>,
+}
+
+impl MemoryAuditStore {
+    /// Creates an empty store
+    pub fn new() -> MemoryAuditStore {
+        MemoryAuditStore::default()
+    }
+
+    /// Gets all entries, oldest first
+    pub fn entries(&self) -> Vec<AuditEntry> {
+        self.entries
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+}
+
+impl AuditStore for MemoryAuditStore {
+    fn append(&mut self, entry: &AuditEntry) -> Result<(), Error> {
+        self.entries
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(entry.clone());
+        Ok(())
+    }
+}
+
+/// Appends the entries as lines to a file, see the formatting of [`AuditEntry`].
+pub struct FileAuditStore {
+    file: File,
+}
+
+impl FileAuditStore {
+    /// Opens the file for appending, it is created if it does not exist.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<FileAuditStore, Error> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(FileAuditStore { file })
+    }
+}
+
+impl AuditStore for FileAuditStore {
+    fn append(&mut self, entry: &AuditEntry) -> Result<(), Error> {
+        writeln!(self.file, "{}", entry)?;
+        self.file.flush()?;
+        Ok(())
+    }
+}
+
+/// Reads the current value of an audited setting
+pub(crate) type ReadSetting<T> = fn(&mut SCD30<T>) -> Result<String, Error>;
+
+impl<T: Transport> SCD30<T> {
+    /// Sets the store calibration-affecting calls are recorded to
+    pub fn set_audit_store(&mut self, store: Box<dyn AuditStore>) {
+        self.audit = Some(store);
+    }
+
+    /// Removes the audit store and gets it back
+    pub fn take_audit_store(&mut self) -> Option<Box<dyn AuditStore>> {
+        self.audit.take()
+    }
+
+    /// Sets the operator note added to the following audit entries
+    pub fn set_audit_note(&mut self, note: Option<String>) {
+        self.audit_note = note;
+    }
+
+    /// Runs a calibration-affecting call and records it if an audit store is set. The setting is
+    /// read before and after the call. The result of the call is returned even if the store fails,
+    /// the failure is only logged as the call cannot be undone.
+    pub(crate) fn audited<F>(
+        &mut self,
+        action: AuditAction,
+        requested: Option<String>,
+        read: Option<ReadSetting<T>>,
+        apply: F,
+    ) -> Result<(), Error>
+    where
+        F: FnOnce(&mut SCD30<T>) -> Result<(), Error>,
+    {
+        if self.audit.is_none() {
+            return apply(self);
+        }
+        let before = read.and_then(|read| read(self).ok());
+        let res = apply(self);
+        let after = match res {
+            Ok(()) => read.and_then(|read| read(self).ok()),
+            Err(_) => None,
+        };
+        let entry = AuditEntry {
+            timestamp: self.clock.system_time(),
+            action,
+            requested,
+            before,
+            after,
+            note: self.audit_note.clone(),
+            error: res.as_ref().err().map(|e| e.to_string()),
+        };
+        if let Some(store) = self.audit.as_mut() {
+            if let Err(e) = store.append(&entry) {
+                warn!("Cannot record audit entry {}: {}", entry, e);
+            }
+        }
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,225 @@
+/*
+MIT License
+
+Copyright (c) 2021 Crispin Tschirky <ct@fhr.ch>
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+ */
+
+use crate::i2c::audit::{AuditAction, AuditEntry, AuditStore, FileAuditStore, MemoryAuditStore};
+use crate::i2c::sim::testing::simulated_sensor;
+use crate::i2c::sim::{Fault, SimulatedSCD30};
+use crate::i2c::{Error, SCD30};
+use std::thread;
+use std::time::{Duration, UNIX_EPOCH};
+
+fn audited_sensor() -> (SCD30<SimulatedSCD30>, SimulatedSCD30, MemoryAuditStore) {
+    let (mut sensor, sim, _) = simulated_sensor();
+    let store = MemoryAuditStore::new();
+    sensor.set_audit_store(Box::new(store.clone()));
+    (sensor, sim, store)
+}
+
+fn some(value: &str) -> Option<String> {
+    Some(value.to_string())
+}
+
+#[test]
+fn test_audit_calibration_calls() {
+    let (mut sensor, _, store) = audited_sensor();
+    sensor.start().unwrap();
+    sensor.set_forced_recalibration(420).unwrap();
+    sensor.enable_self_calibration().unwrap();
+    sensor.disable_self_calibration().unwrap();
+    sensor.set_temperature_offset(1.5).unwrap();
+    sensor.set_altitude_compensation(540).unwrap();
+    sensor.soft_reset().unwrap();
+
+    let entries = store.entries();
+    let summary: Vec<_> = entries
+        .iter()
+        .map(|e| {
+            (
+                e.action,
+                e.requested.clone(),
+                e.before.clone(),
+                e.after.clone(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        vec![
+            (
+                AuditAction::ForcedRecalibration,
+                some("420"),
+                some("400"),
+                some("420")
+            ),
+            (
+                AuditAction::SelfCalibration,
+                some("true"),
+                some("false"),
+                some("true")
+            ),
+            (
+                AuditAction::SelfCalibration,
+                some("false"),
+                some("true"),
+                some("false")
+            ),
+            (
+                AuditAction::TemperatureOffset,
+                some("1.50"),
+                some("0.00"),
+                some("1.50")
+            ),
+            (
+                AuditAction::AltitudeCompensation,
+                some("540"),
+                some("0"),
+                some("540")
+            ),
+            (AuditAction::SoftReset, None, None, None),
+        ],
+        summary
+    );
+    assert!(entries
+        .iter()
+        .all(|e| e.error.is_none() && e.note.is_none()));
+}
+
+#[test]
+fn test_audit_note() {
+    let (mut sensor, _, store) = audited_sensor();
+    sensor.set_audit_note(some("outdoor FRC by jdoe"));
+    sensor.set_altitude_compensation(540).unwrap();
+    sensor.set_audit_note(None);
+    sensor.set_altitude_compensation(550).unwrap();
+
+    let entries = store.entries();
+    assert_eq!(some("outdoor FRC by jdoe"), entries[0].note);
+    assert_eq!(None, entries[1].note);
+}
+
+#[test]
+fn test_audit_failed_call() {
+    let (mut sensor, sim, store) = audited_sensor();
+    sim.inject_fault(Fault::Nack, None);
+    assert!(sensor.set_altitude_compensation(540).is_err());
+
+    let entries = store.entries();
+    assert_eq!(1, entries.len());
+    assert_eq!(None, entries[0].before);
+    assert_eq!(None, entries[0].after);
+    assert!(entries[0].error.as_ref().unwrap().contains("I2C error"));
+}
+
+#[test]
+fn test_no_audit_without_store() {
+    let (mut sensor, _, store) = audited_sensor();
+    assert!(sensor.take_audit_store().is_some());
+    sensor.set_altitude_compensation(540).unwrap();
+    assert!(store.entries().is_empty());
+}
+
+#[test]
+fn test_failing_store_keeps_result() {
+    struct BrokenStore;
+    impl AuditStore for BrokenStore {
+        fn append(&mut self, _entry: &AuditEntry) -> Result<(), Error> {
+            Err(Error::Config("store full".to_string()))
+        }
+    }
+
+    let (mut sensor, sim, _) = audited_sensor();
+    sensor.set_audit_store(Box::new(BrokenStore));
+    // the applied calibration must not look failed, a caller could repeat it
+    sensor.set_altitude_compensation(540).unwrap();
+    assert_eq!(540, sim.altitude());
+    sim.inject_fault(Fault::Nack, None);
+    assert!(sensor.set_altitude_compensation(550).is_err());
+}
+
+#[test]
+fn test_poisoned_memory_store_keeps_recording() {
+    let (mut sensor, _, store) = audited_sensor();
+    let poisoner = store.clone();
+    let _ = thread::spawn(move || {
+        let _entries = poisoner.entries.lock().unwrap();
+        panic!("poisoning the audit store");
+    })
+    .join();
+
+    sensor.set_altitude_compensation(540).unwrap();
+    assert_eq!(1, store.entries().len());
+}
+
+#[test]
+fn test_entry_time_from_clock() {
+    let (mut sensor, _, clock) = simulated_sensor();
+    let store = MemoryAuditStore::new();
+    sensor.set_audit_store(Box::new(store.clone()));
+    clock.set_system_time(UNIX_EPOCH + Duration::from_secs(1_600_000_000));
+    sensor.set_altitude_compensation(540).unwrap();
+    clock.advance(Duration::from_millis(1500));
+    sensor.set_altitude_compensation(550).unwrap();
+
+    let timestamps: Vec<_> = store.entries().iter().map(|e| e.timestamp).collect();
+    assert_eq!(
+        vec![
+            UNIX_EPOCH + Duration::from_secs(1_600_000_000),
+            UNIX_EPOCH + Duration::from_millis(1_600_000_001_500),
+        ],
+        timestamps
+    );
+}
+
+#[test]
+fn test_entry_display() {
+    let entry = AuditEntry {
+        timestamp: UNIX_EPOCH + Duration::from_millis(1_600_000_000_250),
+        action: AuditAction::ForcedRecalibration,
+        requested: some("420"),
+        before: some("400"),
+        after: None,
+        note: some("by\tjdoe\n"),
+        error: None,
+    };
+    assert_eq!(
+        "1600000000.250\tforced_recalibration\t420\t400\t-\tby jdoe \t-",
+        entry.to_string()
+    );
+}
+
+#[test]
+fn test_file_store_appends() {
+    let path = std::env::temp_dir().join(format!("scd30pi-audit-{}.log", std::process::id()));
+    let (mut sensor, _, _) = audited_sensor();
+    sensor.set_audit_store(Box::new(FileAuditStore::open(&path).unwrap()));
+    sensor.set_altitude_compensation(540).unwrap();
+    sensor.set_audit_store(Box::new(FileAuditStore::open(&path).unwrap()));
+    sensor.set_altitude_compensation(550).unwrap();
+
+    let content = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    let lines: Vec<_> = content.lines().collect();
+    assert_eq!(2, lines.len());
+    assert!(lines[0].ends_with("\taltitude_compensation\t540\t0\t540\t-\t-"));
+    assert!(lines[1].ends_with("\taltitude_compensation\t550\t540\t550\t-\t-"));
+}
@@ -0,0 +1,362 @@
+/*
+MIT License
+
+Copyright (c) 2021 Crispin Tschirky <ct@fhr.ch>
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+ */
+
+//! Guided calibration procedures.
+//!
+//! A forced recalibration (FRC) is only accurate if the sensor ran at a stable, known CO2
+//! concentration for at least two minutes. [`SCD30::forced_recalibration`] waits until the
+//! readings are stable, applies the reference value, reads it back and checks that the following
+//! readings converge to the reference.
+//!
+//! ```no_run
+//! use scd30pi::i2c::calibration::ForcedRecalibration;
+//! use scd30pi::i2c::SCD30;
+//!
+//! let mut sensor = SCD30::new().unwrap();
+//! sensor.start().unwrap();
+//! // sensor placed outdoors
+//! let report = sensor
+//!     .forced_recalibration(&ForcedRecalibration::new(420))
+//!     .unwrap();
+//! println!("{:?}", report);
+//! ```
+//!
+//! Heat of the enclosure biases the temperature and thereby the humidity.
+//! [`SCD30::calibrate_temperature_offset`] compares the readings with a
+//! [`ReferenceThermometer`], which can be another sensor driver or a value entered by hand.
+//!
+//! ```no_run
+//! use scd30pi::i2c::calibration::{FixedReference, TemperatureCalibration};
+//! use scd30pi::i2c::SCD30;
+//!
+//! let mut sensor = SCD30::new().unwrap();
+//! sensor.start().unwrap();
+//! let report = sensor
+//!     .calibrate_temperature_offset(&mut FixedReference(21.5), &TemperatureCalibration::default())
+//!     .unwrap();
+//! println!("offset {:.2} °C", report.offset);
+//! ```
+
+use crate::i2c::pressure::Bmp280;
+use crate::i2c::{Error, Transport, FORCED_RECALIBRATION_RANGE, SCD30};
+use log::debug;
+use std::time::{Duration, Instant};
+
+/// Parameters of a forced recalibration.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ForcedRecalibration {
+    /// CO2 concentration in ppm the sensor is exposed to, 400 to 2000 ppm
+    pub reference: u16,
+    /// time the readings have to be stable before the reference is applied
+    pub stable_for: Duration,
+    /// spread in ppm of the readings still considered stable
+    pub max_spread: f32,
+    /// time to wait for stable readings before giving up
+    pub timeout: Duration,
+    /// number of samples read after the recalibration to check the convergence
+    pub verify_samples: usize,
+    /// deviation in ppm of the verification mean from the reference still considered converged
+    pub tolerance: f32,
+}
+
+impl ForcedRecalibration {
+    /// Creates the procedure for the given reference with the timing required by Sensirion
+    pub fn new(reference: u16) -> ForcedRecalibration {
+        ForcedRecalibration {
+            reference,
+            stable_for: Duration::from_secs(120),
+            max_spread: 20.0,
+            timeout: Duration::from_secs(600),
+            verify_samples: 5,
+            tolerance: 25.0,
+        }
+    }
+}
+
+/// Outcome of a forced recalibration.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CalibrationReport {
+    /// reference CO2 concentration in ppm applied
+    pub reference: u16,
+    /// reference read back from the sensor
+    pub readback: u16,
+    /// mean CO2 concentration in ppm of the stable readings before the recalibration
+    pub before: f32,
+    /// mean CO2 concentration in ppm of the verification readings
+    pub after: f32,
+    /// time waited for stable readings
+    pub waited: Duration,
+    /// true if the verification readings are within the tolerance of the reference
+    pub converged: bool,
+}
+
+impl CalibrationReport {
+    /// Gets the correction applied by the recalibration in ppm
+    pub fn correction(&self) -> f32 {
+        f32::from(self.reference) - self.before
+    }
+
+    /// True if the reference was stored and the readings converged
+    pub fn succeeded(&self) -> bool {
+        self.readback == self.reference && self.converged
+    }
+}
+
+/// A source of the true ambient temperature.
+pub trait ReferenceThermometer {
+    /// Reads the temperature in °C
+    fn temperature(&mut self) -> Result<f32, Error>;
+}
+
+/// A temperature read from a reference thermometer by hand.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FixedReference(pub f32);
+
+impl ReferenceThermometer for FixedReference {
+    fn temperature(&mut self) -> Result<f32, Error> {
+        Ok(self.0)
+    }
+}
+
+/// Any closure, e.g. one prompting the user for the reading
+impl<F: FnMut() -> Result<f32, Error>> ReferenceThermometer for F {
+    fn temperature(&mut self) -> Result<f32, Error> {
+        self()
+    }
+}
+
+impl<T: Transport> ReferenceThermometer for Bmp280<T> {
+    fn temperature(&mut self) -> Result<f32, Error> {
+        Ok(self.read()?.0)
+    }
+}
+
+/// Parameters of a temperature offset calibration.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TemperatureCalibration {
+    /// time the readings are compared with the reference
+    pub duration: Duration,
+    /// number of samples compared after the offset is applied
+    pub verify_samples: usize,
+    /// remaining deviation in °C still considered converged
+    pub tolerance: f32,
+}
+
+impl Default for TemperatureCalibration {
+    fn default() -> Self {
+        TemperatureCalibration {
+            duration: Duration::from_secs(300),
+            verify_samples: 5,
+            tolerance: 0.3,
+        }
+    }
+}
+
+/// Outcome of a temperature offset calibration.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OffsetReport {
+    /// offset in °C before the calibration
+    pub previous_offset: f32,
+    /// offset in °C read back after the calibration
+    pub offset: f32,
+    /// mean deviation in °C of the sensor from the reference before the calibration
+    pub deviation_before: f32,
+    /// mean deviation in °C of the sensor from the reference after the calibration
+    pub deviation_after: f32,
+    /// true if the deviation after the calibration is within the tolerance
+    pub converged: bool,
+}
+
+impl<T: Transport> SCD30<T> {
+    /// Compares the temperature with the reference for the given duration, applies the offset
+    /// which compensates the mean deviation and verifies the following readings. Readings flagged
+    /// as stabilizing are ignored. The sensor can only be corrected downwards, a sensor reading
+    /// colder than the reference is reported as [`Error::Config`].
+    pub fn calibrate_temperature_offset<R: ReferenceThermometer>(
+        &mut self,
+        reference: &mut R,
+        calibration: &TemperatureCalibration,
+    ) -> Result<OffsetReport, Error> {
+        if self.measuring_since().is_none() {
+            return Err(Error::Config(
+                "continuous measurement is not running".to_string(),
+            ));
+        }
+        let started = self.clock.now();
+        let mut deviations = Vec::new();
+        while deviations.is_empty() || self.clock.now() - started < calibration.duration {
+            let (timestamp, _) = self.next_co2()?;
+            if self
+                .stable_from
+                .is_some_and(|stable_from| timestamp < stable_from)
+            {
+                continue;
+            }
+            deviations.push(self.temperature - reference.temperature()?);
+        }
+        let deviation_before = mean(&deviations).unwrap_or(0.0);
+
+        let previous_offset = self.read_temperature_offset()?;
+        let offset = ((previous_offset + deviation_before) * 100.0).round() / 100.0;
+        if offset < 0.0 {
+            return Err(Error::Config(format!(
+                "sensor reads {:.2} °C colder than the reference, the offset cannot be negative",
+                -deviation_before
+            )));
+        }
+        self.set_temperature_offset(offset)?;
+        let offset = self.read_temperature_offset()?;
+        debug!(
+            "Temperature offset changed from {:.2} °C to {:.2} °C",
+            previous_offset, offset
+        );
+
+        let mut deviations = Vec::with_capacity(calibration.verify_samples);
+        for _ in 0..calibration.verify_samples {
+            self.next_co2()?;
+            deviations.push(self.temperature - reference.temperature()?);
+        }
+        let deviation_after = mean(&deviations).unwrap_or(f32::NAN);
+        Ok(OffsetReport {
+            previous_offset,
+            offset,
+            deviation_before,
+            deviation_after,
+            converged: deviation_after.abs() <= calibration.tolerance,
+        })
+    }
+
+    /// Runs a forced recalibration: waits until the readings stayed within the spread for the
+    /// given time, applies the reference, reads it back and verifies the following readings.
+    /// Readings flagged as stabilizing are ignored. The continuous measurement must have been
+    /// started by this driver and the reference must be within 400 to 2000 ppm.
+    pub fn forced_recalibration(
+        &mut self,
+        frc: &ForcedRecalibration,
+    ) -> Result<CalibrationReport, Error> {
+        if self.measuring_since().is_none() {
+            return Err(Error::Config(
+                "continuous measurement is not running".to_string(),
+            ));
+        }
+        if !FORCED_RECALIBRATION_RANGE.contains(&frc.reference) {
+            return Err(Error::Config(format!(
+                "reference {} ppm out of range {}..{}",
+                frc.reference,
+                FORCED_RECALIBRATION_RANGE.start(),
+                FORCED_RECALIBRATION_RANGE.end()
+            )));
+        }
+        let started = self.clock.now();
+        let before = self.wait_until_stable(frc, started)?;
+        let waited = self.clock.now() - started;
+
+        self.set_forced_recalibration(frc.reference)?;
+        let readback = self.read_forced_recalibration()?;
+        debug!(
+            "Forced recalibration to {} ppm at {:.0} ppm, read back {}",
+            frc.reference, before, readback
+        );
+
+        let mut after = Vec::with_capacity(frc.verify_samples);
+        for _ in 0..frc.verify_samples {
+            after.push(self.next_co2()?.1);
+        }
+        let after = mean(&after).unwrap_or(f32::NAN);
+        Ok(CalibrationReport {
+            reference: frc.reference,
+            readback,
+            before,
+            after,
+            waited,
+            converged: (after - f32::from(frc.reference)).abs() <= frc.tolerance,
+        })
+    }
+
+    /// Reads samples until they stayed within the spread for the required time. Returns the mean
+    /// CO2 concentration of the stable samples.
+    fn wait_until_stable(
+        &mut self,
+        frc: &ForcedRecalibration,
+        started: Instant,
+    ) -> Result<f32, Error> {
+        let mut window: Vec<(Instant, f32)> = Vec::new();
+        loop {
+            if self.clock.now() - started > frc.timeout {
+                return Err(Error::NoData(format!(
+                    "CO2 readings not stable within {:?}",
+                    frc.timeout
+                )));
+            }
+            let (timestamp, co2) = self.next_co2()?;
+            if self
+                .stable_from
+                .is_some_and(|stable_from| timestamp < stable_from)
+            {
+                continue;
+            }
+            window.push((timestamp, co2));
+            // restart the window at the first sample the new one is within the spread of
+            while spread(&window) > frc.max_spread {
+                window.remove(0);
+            }
+            if timestamp - window[0].0 >= frc.stable_for {
+                let values: Vec<f32> = window.iter().map(|(_, co2)| *co2).collect();
+                return Ok(mean(&values).unwrap_or(co2));
+            }
+        }
+    }
+
+    /// Waits for the next sample and returns its time and CO2 concentration
+    fn next_co2(&mut self) -> Result<(Instant, f32), Error> {
+        let timeout = Duration::from_secs(2 * u64::from(self.interval_in_s.max(1)));
+        self.wait_for_measure(timeout)?;
+        Ok((self.clock.now(), self.co2))
+    }
+}
+
+/// Gets the difference between the highest and the lowest CO2 concentration
+fn spread(window: &[(Instant, f32)]) -> f32 {
+    let min = window
+        .iter()
+        .map(|(_, co2)| *co2)
+        .fold(f32::INFINITY, f32::min);
+    let max = window
+        .iter()
+        .map(|(_, co2)| *co2)
+        .fold(f32::NEG_INFINITY, f32::max);
+    max - min
+}
+
+/// Gets the mean of the values, `None` if there are none
+fn mean(values: &[f32]) -> Option<f32> {
+    if values.is_empty() {
+        None
+    } else {
+        Some(values.iter().sum::<f32>() / values.len() as f32)
+    }
+}
+
+#[cfg(test)]
+mod tests;
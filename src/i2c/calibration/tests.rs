@@ -0,0 +1,253 @@
+/*
+MIT License
+
+Copyright (c) 2021 Crispin Tschirky <ct@fhr.ch>
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+ */
+
+use crate::clock::Clock;
+use crate::i2c::calibration::{FixedReference, ForcedRecalibration, TemperatureCalibration};
+use crate::i2c::sim::testing::simulated_sensor;
+use crate::i2c::sim::{Profile, Scenario};
+use crate::i2c::Error;
+use std::time::Duration;
+
+#[test]
+fn test_forced_recalibration() {
+    let (mut sensor, sim, _) = simulated_sensor();
+    sim.set_environment(420.0, 21.0, 50.0);
+    sim.set_co2_bias(60.0);
+    sensor.start().unwrap();
+
+    let report = sensor
+        .forced_recalibration(&ForcedRecalibration::new(420))
+        .unwrap();
+    assert!(report.succeeded(), "{:?}", report);
+    assert_eq!(420, report.readback);
+    assert_eq!(480.0, report.before);
+    assert_eq!(420.0, report.after);
+    assert_eq!(-60.0, report.correction());
+    // warm-up of 10s and 2 minutes of stable readings
+    assert!(report.waited >= Duration::from_secs(130), "{:?}", report);
+    assert!(report.waited <= Duration::from_secs(136), "{:?}", report);
+    assert_eq!(420, sim.forced_recalibration());
+    assert_eq!(420.0, sensor.co2().unwrap());
+}
+
+#[test]
+fn test_forced_recalibration_requires_measurement() {
+    let (mut sensor, sim, _) = simulated_sensor();
+    match sensor.forced_recalibration(&ForcedRecalibration::new(420)) {
+        Err(Error::Config(_)) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+    assert_eq!(400, sim.forced_recalibration());
+}
+
+#[test]
+fn test_forced_recalibration_reference_out_of_range() {
+    let (mut sensor, sim, clock) = simulated_sensor();
+    sensor.start().unwrap();
+    let start = clock.now();
+    for reference in [399, 2001] {
+        match sensor.forced_recalibration(&ForcedRecalibration::new(reference)) {
+            Err(Error::Config(_)) => {}
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+    // rejected without waiting for stable readings
+    assert_eq!(start, clock.now());
+    assert_eq!(400, sim.forced_recalibration());
+}
+
+#[test]
+fn test_forced_recalibration_waits_for_stable_readings() {
+    let (mut sensor, sim, _) = simulated_sensor();
+    // the concentration settles after three minutes
+    sim.run_scenario(Scenario::new(
+        Profile::constant(900.0).ramp(450.0, Duration::from_secs(180)),
+        Profile::constant(21.0),
+        Profile::constant(50.0),
+    ));
+    sensor.start().unwrap();
+
+    let report = sensor
+        .forced_recalibration(&ForcedRecalibration::new(450))
+        .unwrap();
+    assert!(report.succeeded(), "{:?}", report);
+    assert!(report.waited >= Duration::from_secs(290), "{:?}", report);
+}
+
+#[test]
+fn test_forced_recalibration_timeout() {
+    let (mut sensor, sim, _) = simulated_sensor();
+    sim.run_scenario(Scenario::new(
+        Profile::constant(400.0).ramp(2000.0, Duration::from_secs(3600)),
+        Profile::constant(21.0),
+        Profile::constant(50.0),
+    ));
+    sensor.start().unwrap();
+
+    let frc = ForcedRecalibration {
+        timeout: Duration::from_secs(300),
+        ..ForcedRecalibration::new(420)
+    };
+    match sensor.forced_recalibration(&frc) {
+        Err(Error::NoData(_)) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+    assert_eq!(400, sim.forced_recalibration());
+}
+
+#[test]
+fn test_forced_recalibration_not_converged() {
+    let (mut sensor, sim, _) = simulated_sensor();
+    // the reference gas runs out right after the recalibration
+    sim.run_scenario(Scenario::new(
+        Profile::constant(420.0)
+            .hold(Duration::from_secs(135))
+            .step(900.0),
+        Profile::constant(21.0),
+        Profile::constant(50.0),
+    ));
+    sensor.start().unwrap();
+
+    let report = sensor
+        .forced_recalibration(&ForcedRecalibration::new(420))
+        .unwrap();
+    assert!(!report.converged, "{:?}", report);
+    assert!(!report.succeeded());
+    assert_eq!(420, report.readback);
+}
+
+#[test]
+fn test_read_forced_recalibration() {
+    let (mut sensor, _, _) = simulated_sensor();
+    assert_eq!(400, sensor.read_forced_recalibration().unwrap());
+    sensor.start().unwrap();
+    sensor.set_forced_recalibration(650).unwrap();
+    assert_eq!(650, sensor.read_forced_recalibration().unwrap());
+}
+
+#[test]
+fn test_temperature_offset_calibration() {
+    let (mut sensor, sim, _) = simulated_sensor();
+    sim.set_environment(600.0, 25.3, 40.0);
+    sensor.set_temperature_offset(1.0).unwrap();
+    sensor.start().unwrap();
+
+    let report = sensor
+        .calibrate_temperature_offset(
+            &mut FixedReference(21.5),
+            &TemperatureCalibration::default(),
+        )
+        .unwrap();
+    assert!(report.converged, "{:?}", report);
+    assert_eq!(1.0, report.previous_offset);
+    assert_eq!(3.8, report.offset);
+    assert!((report.deviation_before - 2.8).abs() < 0.01, "{:?}", report);
+    assert!(report.deviation_after.abs() < 0.01, "{:?}", report);
+    assert_eq!(3.8, sim.temperature_offset());
+}
+
+#[test]
+fn test_temperature_offset_from_closure() {
+    let (mut sensor, sim, _) = simulated_sensor();
+    sim.set_environment(600.0, 24.0, 40.0);
+    sensor.start().unwrap();
+
+    let mut readings = 0;
+    let mut reference = || {
+        readings += 1;
+        Ok(22.0)
+    };
+    let report = sensor
+        .calibrate_temperature_offset(&mut reference, &TemperatureCalibration::default())
+        .unwrap();
+    assert!(report.converged, "{:?}", report);
+    assert_eq!(2.0, report.offset);
+    // one reading per sample from the end of the warm-up at 10s to 300s and per verification sample
+    assert_eq!(146 + 5, readings);
+}
+
+#[test]
+fn test_temperature_offset_not_converged() {
+    let (mut sensor, sim, _) = simulated_sensor();
+    // the enclosure heats up right after the offset is applied
+    sim.run_scenario(Scenario::new(
+        Profile::constant(600.0),
+        Profile::constant(24.0)
+            .hold(Duration::from_secs(65))
+            .step(26.0),
+        Profile::constant(40.0),
+    ));
+    sensor.start().unwrap();
+
+    let calibration = TemperatureCalibration {
+        duration: Duration::from_secs(60),
+        ..TemperatureCalibration::default()
+    };
+    let report = sensor
+        .calibrate_temperature_offset(&mut FixedReference(22.0), &calibration)
+        .unwrap();
+    assert_eq!(2.0, report.offset);
+    assert!(!report.converged, "{:?}", report);
+}
+
+#[test]
+fn test_temperature_offset_cannot_be_negative() {
+    let (mut sensor, sim, _) = simulated_sensor();
+    sim.set_environment(600.0, 20.0, 40.0);
+    sensor.start().unwrap();
+
+    match sensor.calibrate_temperature_offset(
+        &mut FixedReference(21.0),
+        &TemperatureCalibration::default(),
+    ) {
+        Err(Error::Config(_)) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+    assert_eq!(0.0, sim.temperature_offset());
+}
+
+#[test]
+fn test_temperature_offset_out_of_range() {
+    let (mut sensor, sim, _) = simulated_sensor();
+    sensor.set_temperature_offset(655.35).unwrap();
+    assert_eq!(655.35, sim.temperature_offset());
+    for offset in [-0.01, 655.36, f32::NAN, f32::INFINITY] {
+        match sensor.set_temperature_offset(offset) {
+            Err(Error::Config(_)) => {}
+            other => panic!("unexpected result {:?} for {}", other, offset),
+        }
+    }
+    assert_eq!(655.35, sim.temperature_offset());
+}
+
+#[test]
+fn test_temperature_offset_requires_measurement() {
+    let (mut sensor, _, _) = simulated_sensor();
+    assert!(sensor
+        .calibrate_temperature_offset(
+            &mut FixedReference(21.0),
+            &TemperatureCalibration::default()
+        )
+        .is_err());
+}
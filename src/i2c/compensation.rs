@@ -0,0 +1,199 @@
+/*
+MIT License
+
+Copyright (c) 2021 Crispin Tschirky <ct@fhr.ch>
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+ */
+
+//! Altitude and pressure compensation setup.
+//!
+//! The SCD30 compensates the CO2 reading either for the site altitude, which is stored in the
+//! sensor, or for the ambient pressure passed with the start of the measurement. A pressure given
+//! at the start overrides the altitude, a start without pressure falls back to the altitude. A
+//! [`Compensation`] describes what the installer knows about the site and
+//! [`SCD30::apply_compensation`] issues the matching commands.
+//!
+//! ```no_run
+//! use scd30pi::i2c::compensation::Compensation;
+//! use scd30pi::i2c::SCD30;
+//!
+//! let mut sensor = SCD30::new().unwrap();
+//! // QNH of the nearest airport and the altitude of the site
+//! let active = sensor
+//!     .apply_compensation(&Compensation::Qnh { qnh: 1021.0, altitude: 540 })
+//!     .unwrap();
+//! println!("compensating {}", active);
+//! ```
+
+use crate::i2c::{Error, Transport, PRESSURE_COMPENSATION_RANGE, SCD30};
+use std::fmt;
+
+/// Pressure of the standard atmosphere at sea level in hPa (= mbar)
+pub const STANDARD_SEA_LEVEL_PRESSURE: f32 = 1013.25;
+/// Highest altitude of a site in meters accepted for compensation, the barometric formula only
+/// holds in the troposphere
+pub const MAX_ALTITUDE: u16 = 10_000;
+
+/// Gets the pressure in hPa at the given altitude in meters with the barometric formula of the
+/// standard atmosphere, given the pressure reduced to sea level (QNH).
+pub fn pressure_at_altitude(altitude: f32, sea_level_pressure: f32) -> f32 {
+    sea_level_pressure * (1.0 - 2.25577e-5 * altitude).powf(5.25588)
+}
+
+/// Gets the altitude in meters at which the given pressure is measured, given the pressure reduced
+/// to sea level (QNH). Inverse of [`pressure_at_altitude`].
+pub fn altitude_from_pressure(pressure: f32, sea_level_pressure: f32) -> f32 {
+    (1.0 - (pressure / sea_level_pressure).powf(1.0 / 5.25588)) / 2.25577e-5
+}
+
+/// What is known about the site of a sensor.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Compensation {
+    /// No compensation, the sensor assumes sea level
+    None,
+    /// Altitude of the site in meters above sea level
+    Altitude(u16),
+    /// Ambient pressure measured at the site in hPa (= mbar)
+    StationPressure(f32),
+    /// Pressure reduced to sea level in hPa, e.g. from a weather service, and the altitude of the
+    /// site in meters. The pressure at the site is derived from both, the altitude is kept as
+    /// fallback.
+    Qnh { qnh: f32, altitude: u16 },
+}
+
+impl Compensation {
+    /// Gets the ambient pressure at the site in hPa, for an altitude of the standard atmosphere
+    pub fn station_pressure(&self) -> f32 {
+        match *self {
+            Compensation::None => STANDARD_SEA_LEVEL_PRESSURE,
+            Compensation::Altitude(altitude) => {
+                pressure_at_altitude(f32::from(altitude), STANDARD_SEA_LEVEL_PRESSURE)
+            }
+            Compensation::StationPressure(pressure) => pressure,
+            Compensation::Qnh { qnh, altitude } => pressure_at_altitude(f32::from(altitude), qnh),
+        }
+    }
+
+    /// Gets the altitude of the site in meters, for a pressure of the standard atmosphere
+    pub fn altitude(&self) -> f32 {
+        match *self {
+            Compensation::None => 0.0,
+            Compensation::Altitude(altitude) | Compensation::Qnh { altitude, .. } => {
+                f32::from(altitude)
+            }
+            Compensation::StationPressure(pressure) => {
+                altitude_from_pressure(pressure, STANDARD_SEA_LEVEL_PRESSURE)
+            }
+        }
+    }
+}
+
+/// Compensation the sensor currently applies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ActiveCompensation {
+    /// No compensation
+    None,
+    /// Altitude compensation in meters
+    Altitude(u16),
+    /// Pressure compensation in mbar, overrides the altitude
+    Pressure(u16),
+}
+
+impl fmt::Display for ActiveCompensation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ActiveCompensation::None => write!(f, "none"),
+            ActiveCompensation::Altitude(altitude) => write!(f, "altitude {} m", altitude),
+            ActiveCompensation::Pressure(pressure) => write!(f, "pressure {} mbar", pressure),
+        }
+    }
+}
+
+/// Rounds a pressure to the mbar the sensor accepts
+pub(crate) fn to_mbar(pressure: f32) -> Result<u16, Error> {
+    let mbar = pressure.round();
+    if pressure.is_finite()
+        && f32::from(*PRESSURE_COMPENSATION_RANGE.start()) <= mbar
+        && mbar <= f32::from(*PRESSURE_COMPENSATION_RANGE.end())
+    {
+        Ok(mbar as u16)
+    } else {
+        Err(Error::InvalidData(format!(
+            "pressure {} mbar out of range {:?}",
+            pressure, PRESSURE_COMPENSATION_RANGE
+        )))
+    }
+}
+
+impl<T: Transport> SCD30<T> {
+    /// Applies the compensation and (re)starts the continuous measurement. An altitude is stored
+    /// in the sensor and the measurement is started without pressure, a pressure is passed with
+    /// the start. Returns the compensation which is active now. Altitudes above [`MAX_ALTITUDE`]
+    /// are rejected.
+    pub fn apply_compensation(
+        &mut self,
+        compensation: &Compensation,
+    ) -> Result<ActiveCompensation, Error> {
+        if let Compensation::Altitude(altitude) | Compensation::Qnh { altitude, .. } = *compensation
+        {
+            if altitude > MAX_ALTITUDE {
+                return Err(Error::Config(format!(
+                    "altitude {} m above {} m",
+                    altitude, MAX_ALTITUDE
+                )));
+            }
+        }
+        match *compensation {
+            Compensation::None => {
+                self.set_altitude_compensation(0)?;
+                self.start()?;
+            }
+            Compensation::Altitude(altitude) => {
+                self.set_altitude_compensation(altitude)?;
+                self.start()?;
+            }
+            Compensation::StationPressure(pressure) => {
+                let mbar = to_mbar(pressure)?;
+                self.start_with_alt_comp(mbar)?;
+            }
+            Compensation::Qnh { altitude, .. } => {
+                let mbar = to_mbar(compensation.station_pressure())?;
+                self.set_altitude_compensation(altitude)?;
+                self.start_with_alt_comp(mbar)?;
+            }
+        }
+        self.active_compensation()
+    }
+
+    /// Gets the compensation the sensor applies. The pressure is only known if the measurement was
+    /// started by this driver, otherwise the altitude stored in the sensor is reported.
+    pub fn active_compensation(&mut self) -> Result<ActiveCompensation, Error> {
+        if self.pressure_compensation() != 0 {
+            return Ok(ActiveCompensation::Pressure(self.pressure_compensation()));
+        }
+        match self.read_altitude_compensation()? {
+            0 => Ok(ActiveCompensation::None),
+            altitude => Ok(ActiveCompensation::Altitude(altitude)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,155 @@
+/*
+MIT License
+
+Copyright (c) 2021 Crispin Tschirky <ct@fhr.ch>
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+ */
+
+use crate::i2c::compensation::{
+    altitude_from_pressure, pressure_at_altitude, ActiveCompensation, Compensation, MAX_ALTITUDE,
+    STANDARD_SEA_LEVEL_PRESSURE,
+};
+use crate::i2c::sim::testing::simulated_sensor;
+use crate::i2c::Error;
+
+#[test]
+fn test_barometric_formula() {
+    assert_eq!(
+        STANDARD_SEA_LEVEL_PRESSURE,
+        pressure_at_altitude(0.0, STANDARD_SEA_LEVEL_PRESSURE)
+    );
+    // standard atmosphere tables
+    assert!((pressure_at_altitude(1000.0, STANDARD_SEA_LEVEL_PRESSURE) - 898.76).abs() < 0.1);
+    assert!((pressure_at_altitude(3000.0, STANDARD_SEA_LEVEL_PRESSURE) - 701.12).abs() < 0.1);
+    assert!((altitude_from_pressure(898.76, STANDARD_SEA_LEVEL_PRESSURE) - 1000.0).abs() < 1.0);
+}
+
+#[test]
+fn test_conversion_round_trip() {
+    for altitude in &[0.0, 250.0, 540.0, 1800.0, 2500.0] {
+        let pressure = pressure_at_altitude(*altitude, 1021.0);
+        assert!((altitude_from_pressure(pressure, 1021.0) - altitude).abs() < 0.5);
+    }
+}
+
+#[test]
+fn test_compensation_conversions() {
+    assert_eq!(0.0, Compensation::None.altitude());
+    assert_eq!(1013.25, Compensation::None.station_pressure());
+    assert!((Compensation::Altitude(1000).station_pressure() - 898.76).abs() < 0.1);
+    assert!((Compensation::StationPressure(898.76).altitude() - 1000.0).abs() < 1.0);
+
+    let qnh = Compensation::Qnh {
+        qnh: 1030.0,
+        altitude: 1000,
+    };
+    assert_eq!(1000.0, qnh.altitude());
+    assert!(qnh.station_pressure() > Compensation::Altitude(1000).station_pressure());
+}
+
+#[test]
+fn test_apply_altitude() {
+    let (mut sensor, sim, _) = simulated_sensor();
+    sensor.start_with_alt_comp(950).unwrap();
+
+    let active = sensor
+        .apply_compensation(&Compensation::Altitude(540))
+        .unwrap();
+    assert_eq!(ActiveCompensation::Altitude(540), active);
+    assert_eq!(540, sim.altitude());
+    assert_eq!(Some(0), sim.measuring());
+}
+
+#[test]
+fn test_apply_station_pressure() {
+    let (mut sensor, sim, _) = simulated_sensor();
+    let active = sensor
+        .apply_compensation(&Compensation::StationPressure(953.6))
+        .unwrap();
+    assert_eq!(ActiveCompensation::Pressure(954), active);
+    assert_eq!(Some(954), sim.measuring());
+    assert_eq!("pressure 954 mbar", active.to_string());
+}
+
+#[test]
+fn test_apply_qnh() {
+    let (mut sensor, sim, _) = simulated_sensor();
+    let active = sensor
+        .apply_compensation(&Compensation::Qnh {
+            qnh: 1013.25,
+            altitude: 1000,
+        })
+        .unwrap();
+    assert_eq!(ActiveCompensation::Pressure(899), active);
+    assert_eq!(Some(899), sim.measuring());
+    assert_eq!(1000, sim.altitude());
+
+    // the altitude takes over once the measurement is started without pressure
+    sensor.start().unwrap();
+    assert_eq!(
+        ActiveCompensation::Altitude(1000),
+        sensor.active_compensation().unwrap()
+    );
+}
+
+#[test]
+fn test_apply_none() {
+    let (mut sensor, sim, _) = simulated_sensor();
+    sensor.set_altitude_compensation(300).unwrap();
+    let active = sensor.apply_compensation(&Compensation::None).unwrap();
+    assert_eq!(ActiveCompensation::None, active);
+    assert_eq!(0, sim.altitude());
+    assert_eq!("none", active.to_string());
+}
+
+#[test]
+fn test_apply_pressure_out_of_range() {
+    let (mut sensor, sim, _) = simulated_sensor();
+    match sensor.apply_compensation(&Compensation::StationPressure(600.0)) {
+        Err(Error::InvalidData(_)) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+    assert_eq!(None, sim.measuring());
+}
+
+#[test]
+fn test_apply_altitude_out_of_range() {
+    let (mut sensor, sim, _) = simulated_sensor();
+    sensor
+        .apply_compensation(&Compensation::Altitude(MAX_ALTITUDE))
+        .unwrap();
+    sensor.stop().unwrap();
+
+    for compensation in [
+        Compensation::Altitude(MAX_ALTITUDE + 1),
+        Compensation::Altitude(50_000),
+        Compensation::Qnh {
+            qnh: 1013.25,
+            altitude: 50_000,
+        },
+    ] {
+        match sensor.apply_compensation(&compensation) {
+            Err(Error::Config(_)) => {}
+            other => panic!("unexpected {:?} for {:?}", other, compensation),
+        }
+    }
+    assert_eq!(MAX_ALTITUDE, sim.altitude());
+    assert_eq!(None, sim.measuring());
+}
@@ -0,0 +1,293 @@
+/*
+MIT License
+
+Copyright (c) 2021 Crispin Tschirky <ct@fhr.ch>
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+ */
+
+//! Persists the sensor configuration to a file and restores it to a sensor.
+//!
+//! The configuration is stored in a flat TOML file such that a replaced sensor can be brought to the
+//! same state as its predecessor:
+//!
+//! ```toml
+//! firmware_version = "3.66"
+//! measure_interval = 2
+//! self_calibration = true
+//! altitude_compensation = 450
+//! temperature_offset = 1.5
+//! pressure_compensation = 0
+//! ```
+
+use crate::i2c::{
+    Error, Transport, MEASURE_INTERVAL_RANGE, PRESSURE_COMPENSATION_RANGE, SCD30,
+    TEMPERATURE_OFFSET_RANGE,
+};
+use log::debug;
+use std::fs;
+use std::path::Path;
+
+/// Configuration of a sensor as it can be read from and applied to the device.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SensorConfig {
+    /// firmware version of the sensor the configuration was read from, e.g. "3.66"
+    pub firmware_version: String,
+    /// measure interval in seconds
+    pub measure_interval: u16,
+    /// true if the automatic self calibration is enabled
+    pub self_calibration: bool,
+    /// altitude compensation in meters above sea level
+    pub altitude_compensation: u16,
+    /// temperature offset in °C
+    pub temperature_offset: f32,
+    /// ambient pressure in mbar the measurement is started with, 0 if not compensated
+    pub pressure_compensation: u16,
+}
+
+impl SensorConfig {
+    /// Loads a configuration from the given TOML file.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<SensorConfig, Error> {
+        let content = fs::read_to_string(path)?;
+        SensorConfig::from_toml(&content)
+    }
+
+    /// Saves the configuration to the given TOML file.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        fs::write(path, self.to_toml())?;
+        Ok(())
+    }
+
+    /// Serialises the configuration to TOML.
+    pub fn to_toml(&self) -> String {
+        format!(
+            "# SCD30 sensor configuration\n\
+             firmware_version = \"{}\"\n\
+             measure_interval = {}\n\
+             self_calibration = {}\n\
+             altitude_compensation = {}\n\
+             temperature_offset = {:.2}\n\
+             pressure_compensation = {}\n",
+            self.firmware_version,
+            self.measure_interval,
+            self.self_calibration,
+            self.altitude_compensation,
+            self.temperature_offset,
+            self.pressure_compensation
+        )
+    }
+
+    /// Parses a configuration from TOML. All keys are mandatory, unknown keys are rejected.
+    pub fn from_toml(content: &str) -> Result<SensorConfig, Error> {
+        let mut firmware_version = None;
+        let mut measure_interval = None;
+        let mut self_calibration = None;
+        let mut altitude_compensation = None;
+        let mut temperature_offset = None;
+        let mut pressure_compensation = None;
+
+        for (idx, raw_line) in content.lines().enumerate() {
+            let line = strip_comment(raw_line).trim();
+            if line.is_empty() {
+                continue;
+            }
+            let mut parts = line.splitn(2, '=');
+            let key = parts.next().unwrap_or("").trim();
+            let value = match parts.next() {
+                Some(v) => v.trim(),
+                None => {
+                    return Err(Error::Config(format!(
+                        "line {}: expected 'key = value'",
+                        idx + 1
+                    )))
+                }
+            };
+            match key {
+                "firmware_version" => firmware_version = Some(parse_string(key, value)?),
+                "measure_interval" => measure_interval = Some(parse_value::<u16>(key, value)?),
+                "self_calibration" => self_calibration = Some(parse_value::<bool>(key, value)?),
+                "altitude_compensation" => {
+                    altitude_compensation = Some(parse_value::<u16>(key, value)?)
+                }
+                "temperature_offset" => temperature_offset = Some(parse_value::<f32>(key, value)?),
+                "pressure_compensation" => {
+                    pressure_compensation = Some(parse_value::<u16>(key, value)?)
+                }
+                _ => {
+                    return Err(Error::Config(format!(
+                        "line {}: unknown key '{}'",
+                        idx + 1,
+                        key
+                    )))
+                }
+            }
+        }
+
+        let config = SensorConfig {
+            firmware_version: required("firmware_version", firmware_version)?,
+            measure_interval: required("measure_interval", measure_interval)?,
+            self_calibration: required("self_calibration", self_calibration)?,
+            altitude_compensation: required("altitude_compensation", altitude_compensation)?,
+            temperature_offset: required("temperature_offset", temperature_offset)?,
+            pressure_compensation: required("pressure_compensation", pressure_compensation)?,
+        };
+        config.check_ranges()?;
+        Ok(config)
+    }
+
+    /// Checks that the configuration can be applied to a sensor running the given firmware. The
+    /// configuration is accepted if the major firmware versions match.
+    pub fn validate_for(&self, firmware_version: &str) -> Result<(), Error> {
+        self.check_ranges()?;
+        let expected = major_version(&self.firmware_version)?;
+        let actual = major_version(firmware_version)?;
+        if expected != actual {
+            return Err(Error::Config(format!(
+                "configuration was made for firmware {} but sensor runs {}",
+                self.firmware_version, firmware_version
+            )));
+        }
+        Ok(())
+    }
+
+    /// Checks the values against the ranges supported by the sensor.
+    fn check_ranges(&self) -> Result<(), Error> {
+        if !MEASURE_INTERVAL_RANGE.contains(&self.measure_interval) {
+            return Err(Error::Config(format!(
+                "measure_interval {} out of range {}..{}",
+                self.measure_interval,
+                MEASURE_INTERVAL_RANGE.start(),
+                MEASURE_INTERVAL_RANGE.end()
+            )));
+        }
+        if !TEMPERATURE_OFFSET_RANGE.contains(&self.temperature_offset) {
+            return Err(Error::Config(format!(
+                "temperature_offset {} out of range {}..{}",
+                self.temperature_offset,
+                TEMPERATURE_OFFSET_RANGE.start(),
+                TEMPERATURE_OFFSET_RANGE.end()
+            )));
+        }
+        if self.pressure_compensation != 0
+            && !PRESSURE_COMPENSATION_RANGE.contains(&self.pressure_compensation)
+        {
+            return Err(Error::Config(format!(
+                "pressure_compensation {} out of range {}..{}",
+                self.pressure_compensation,
+                PRESSURE_COMPENSATION_RANGE.start(),
+                PRESSURE_COMPENSATION_RANGE.end()
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl<T: Transport> SCD30<T> {
+    /// Reads the current configuration from the sensor. The pressure compensation is not readable
+    /// from the device, the value last passed to [`SCD30::start_with_alt_comp`] is used instead.
+    pub fn read_config(&mut self) -> Result<SensorConfig, Error> {
+        Ok(SensorConfig {
+            firmware_version: self.read_firmware_version()?,
+            measure_interval: self.read_measure_interval()?,
+            self_calibration: self.read_self_calibration()?,
+            altitude_compensation: self.read_altitude_compensation()?,
+            temperature_offset: self.read_temperature_offset()?,
+            pressure_compensation: self.pressure_compensation(),
+        })
+    }
+
+    /// Applies the given configuration to the sensor. If a pressure compensation is configured the
+    /// continuous measurement is (re)started with it. A running measurement is restarted without
+    /// pressure compensation if none is configured.
+    pub fn apply_config(&mut self, config: &SensorConfig) -> Result<(), Error> {
+        config.check_ranges()?;
+        self.set_measure_interval(config.measure_interval)?;
+        if config.self_calibration {
+            self.enable_self_calibration()?;
+        } else {
+            self.disable_self_calibration()?;
+        }
+        self.set_altitude_compensation(config.altitude_compensation)?;
+        self.set_temperature_offset(config.temperature_offset)?;
+        if config.pressure_compensation != 0 || self.measuring_since.is_some() {
+            self.start_with_alt_comp(config.pressure_compensation)?;
+        }
+        debug!("Applied configuration {:?}", config);
+        Ok(())
+    }
+
+    /// Saves the current sensor configuration to the given file.
+    pub fn save_config<P: AsRef<Path>>(&mut self, path: P) -> Result<SensorConfig, Error> {
+        let config = self.read_config()?;
+        config.save(path)?;
+        Ok(config)
+    }
+
+    /// Loads a configuration from the given file, validates it against the firmware of the connected
+    /// sensor and applies it.
+    pub fn restore_config<P: AsRef<Path>>(&mut self, path: P) -> Result<SensorConfig, Error> {
+        let config = SensorConfig::load(path)?;
+        let firmware_version = self.read_firmware_version()?;
+        config.validate_for(&firmware_version)?;
+        self.apply_config(&config)?;
+        Ok(config)
+    }
+}
+
+/// Removes a trailing comment which is not part of a quoted string.
+fn strip_comment(line: &str) -> &str {
+    let mut in_string = false;
+    for (idx, c) in line.char_indices() {
+        match c {
+            '"' => in_string = !in_string,
+            '#' if !in_string => return &line[..idx],
+            _ => {}
+        }
+    }
+    line
+}
+
+fn parse_string(key: &str, value: &str) -> Result<String, Error> {
+    if value.len() >= 2 && value.starts_with('"') && value.ends_with('"') {
+        Ok(value[1..value.len() - 1].to_string())
+    } else {
+        Err(Error::Config(format!("{}: expected a quoted string", key)))
+    }
+}
+
+fn parse_value<T: std::str::FromStr>(key: &str, value: &str) -> Result<T, Error> {
+    value
+        .parse::<T>()
+        .map_err(|_| Error::Config(format!("{}: invalid value '{}'", key, value)))
+}
+
+fn required<T>(key: &str, value: Option<T>) -> Result<T, Error> {
+    value.ok_or_else(|| Error::Config(format!("missing key '{}'", key)))
+}
+
+fn major_version(version: &str) -> Result<u8, Error> {
+    version
+        .split('.')
+        .next()
+        .and_then(|major| major.parse::<u8>().ok())
+        .ok_or_else(|| Error::Config(format!("invalid firmware version '{}'", version)))
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,120 @@
+/*
+MIT License
+
+Copyright (c) 2021 Crispin Tschirky <ct@fhr.ch>
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+ */
+
+use crate::i2c::config::SensorConfig;
+use crate::i2c::sim::testing::simulated_sensor;
+
+fn sample_config() -> SensorConfig {
+    SensorConfig {
+        firmware_version: "3.66".to_string(),
+        measure_interval: 5,
+        self_calibration: true,
+        altitude_compensation: 450,
+        temperature_offset: 1.5,
+        pressure_compensation: 965,
+    }
+}
+
+#[test]
+fn test_config_toml_round_trip() {
+    let config = sample_config();
+    let parsed = SensorConfig::from_toml(&config.to_toml()).unwrap();
+    assert_eq!(config, parsed);
+}
+
+#[test]
+fn test_config_from_toml_with_comments() {
+    let toml = r#"
+        # replacement for room 12
+        firmware_version = "3.66" # read from device
+        measure_interval = 2
+        self_calibration = false
+        altitude_compensation = 0
+        temperature_offset = 0.0
+        pressure_compensation = 0
+    "#;
+    let config = SensorConfig::from_toml(toml).unwrap();
+    assert_eq!("3.66", config.firmware_version);
+    assert_eq!(2, config.measure_interval);
+    assert!(!config.self_calibration);
+}
+
+#[test]
+fn test_config_missing_key() {
+    let toml = sample_config()
+        .to_toml()
+        .replace("measure_interval = 5\n", "");
+    assert!(SensorConfig::from_toml(&toml).is_err());
+}
+
+#[test]
+fn test_config_unknown_key() {
+    let toml = format!("{}colour = 3\n", sample_config().to_toml());
+    assert!(SensorConfig::from_toml(&toml).is_err());
+}
+
+#[test]
+fn test_config_interval_out_of_range() {
+    let toml = sample_config()
+        .to_toml()
+        .replace("measure_interval = 5", "measure_interval = 1");
+    assert!(SensorConfig::from_toml(&toml).is_err());
+}
+
+#[test]
+fn test_config_validate_firmware() {
+    let config = sample_config();
+    assert!(config.validate_for("3.42").is_ok());
+    assert!(config.validate_for("4.0").is_err());
+    assert!(config.validate_for("garbage").is_err());
+}
+
+#[test]
+fn test_config_save_and_load() {
+    let path = std::env::temp_dir().join(format!("scd30pi-config-{}.toml", std::process::id()));
+    let config = sample_config();
+    config.save(&path).unwrap();
+    let loaded = SensorConfig::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(config, loaded);
+}
+
+#[test]
+fn test_apply_config_without_pressure_compensation() {
+    let (mut sensor, sim, _) = simulated_sensor();
+
+    // a stopped sensor is not started
+    let config = SensorConfig {
+        pressure_compensation: 0,
+        ..sample_config()
+    };
+    sensor.apply_config(&config).unwrap();
+    assert_eq!(None, sim.measuring());
+
+    // a running pressure compensation is removed
+    sensor.start_with_alt_comp(950).unwrap();
+    sensor.apply_config(&config).unwrap();
+    assert_eq!(Some(0), sim.measuring());
+    assert_eq!(0, sensor.read_config().unwrap().pressure_compensation);
+}
@@ -0,0 +1,361 @@
+/*
+MIT License
+
+Copyright (c) 2021 Crispin Tschirky <ct@fhr.ch>
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+ */
+
+//! Self-diagnostics of a sensor.
+//!
+//! [`SCD30::diagnose`] runs a series of checks and returns a [`DiagnosticReport`] which can be
+//! printed or evaluated remotely before a technician is dispatched. The checks take about
+//! `cadence_samples + 1` measure intervals as they wait for new samples.
+
+use crate::i2c::{
+    Error, Transport, CMD_GET_DATA_READY, CMD_RESPONSE_DELAY, MEASURE_INTERVAL_RANGE, SCD30,
+};
+use std::fmt;
+use std::time::Duration;
+
+/// Result of a single check. The order is from best to worst.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Status {
+    Pass,
+    Warn,
+    Fail,
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Status::Pass => write!(f, "PASS"),
+            Status::Warn => write!(f, "WARN"),
+            Status::Fail => write!(f, "FAIL"),
+        }
+    }
+}
+
+/// Outcome of a single check.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiagnosticEntry {
+    /// name of the check
+    pub check: &'static str,
+    /// result of the check
+    pub status: Status,
+    /// human readable details
+    pub detail: String,
+}
+
+/// Outcome of all checks.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct DiagnosticReport {
+    pub entries: Vec<DiagnosticEntry>,
+}
+
+impl DiagnosticReport {
+    /// Gets the worst status of all checks
+    pub fn status(&self) -> Status {
+        self.entries
+            .iter()
+            .map(|e| e.status)
+            .max()
+            .unwrap_or(Status::Pass)
+    }
+
+    /// Gets the entry of the given check
+    pub fn entry(&self, check: &str) -> Option<&DiagnosticEntry> {
+        self.entries.iter().find(|e| e.check == check)
+    }
+
+    fn add(&mut self, check: &'static str, status: Status, detail: String) {
+        self.entries.push(DiagnosticEntry {
+            check,
+            status,
+            detail,
+        });
+    }
+}
+
+impl fmt::Display for DiagnosticReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for entry in &self.entries {
+            writeln!(f, "{} {}: {}", entry.status, entry.check, entry.detail)?;
+        }
+        write!(f, "overall: {}", self.status())
+    }
+}
+
+/// Parameters of the diagnostics.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DiagnosticOptions {
+    /// number of samples timed to check the data ready cadence
+    pub cadence_samples: usize,
+    /// number of reads in the CRC error burst
+    pub burst_reads: usize,
+    /// error rate of the burst up to which the check only warns
+    pub max_warn_error_rate: f32,
+    /// relative deviation of the sample period from the measure interval tolerated
+    pub cadence_tolerance: f32,
+}
+
+impl Default for DiagnosticOptions {
+    fn default() -> Self {
+        DiagnosticOptions {
+            cadence_samples: 3,
+            burst_reads: 20,
+            max_warn_error_rate: 0.05,
+            cadence_tolerance: 0.2,
+        }
+    }
+}
+
+impl<T: Transport> SCD30<T> {
+    /// Runs all checks with the default options.
+    pub fn diagnose(&mut self) -> DiagnosticReport {
+        self.diagnose_with(&DiagnosticOptions::default())
+    }
+
+    /// Runs all checks: bus reachability, firmware version, configuration sanity, data ready
+    /// cadence, CRC error rate and plausibility of the values. If the sensor is not reachable the
+    /// other checks are skipped.
+    pub fn diagnose_with(&mut self, options: &DiagnosticOptions) -> DiagnosticReport {
+        let mut report = DiagnosticReport::default();
+
+        let firmware_version = match self.read_firmware_version() {
+            Ok(version) => {
+                report.add("bus", Status::Pass, "sensor answers".to_string());
+                version
+            }
+            Err(e) => {
+                report.add("bus", Status::Fail, format!("sensor not reachable: {}", e));
+                return report;
+            }
+        };
+
+        self.check_firmware(&mut report, &firmware_version);
+        self.check_configuration(&mut report);
+        self.check_cadence(&mut report, options);
+        self.check_crc_rate(&mut report, options);
+        self.check_plausibility(&mut report);
+        report
+    }
+
+    fn check_firmware(&mut self, report: &mut DiagnosticReport, version: &str) {
+        let major = version.split('.').next().and_then(|m| m.parse::<u8>().ok());
+        match major {
+            Some(major) if major >= 3 => {
+                report.add("firmware", Status::Pass, format!("version {}", version))
+            }
+            Some(_) => report.add(
+                "firmware",
+                Status::Warn,
+                format!("version {} is outdated", version),
+            ),
+            None => report.add(
+                "firmware",
+                Status::Fail,
+                format!("invalid version {}", version),
+            ),
+        }
+    }
+
+    fn check_configuration(&mut self, report: &mut DiagnosticReport) {
+        let res = (|| -> Result<(u16, u16, f32, bool), Error> {
+            Ok((
+                self.read_measure_interval()?,
+                self.read_altitude_compensation()?,
+                self.read_temperature_offset()?,
+                self.read_self_calibration()?,
+            ))
+        })();
+        let (interval, altitude, offset, asc) = match res {
+            Ok(values) => values,
+            Err(e) => {
+                report.add(
+                    "configuration",
+                    Status::Fail,
+                    format!("cannot read configuration: {}", e),
+                );
+                return;
+            }
+        };
+        let detail = format!(
+            "interval {} s, altitude {} m, temperature offset {:.2} °C, self calibration {}",
+            interval,
+            altitude,
+            offset,
+            if asc { "on" } else { "off" }
+        );
+        let mut problems = Vec::new();
+        if !MEASURE_INTERVAL_RANGE.contains(&interval) {
+            problems.push(format!(
+                "interval outside {}..{} s",
+                MEASURE_INTERVAL_RANGE.start(),
+                MEASURE_INTERVAL_RANGE.end()
+            ));
+        }
+        if altitude > 5000 {
+            problems.push("altitude above 5000 m".to_string());
+        }
+        if offset > 10.0 {
+            problems.push("temperature offset above 10 °C".to_string());
+        }
+        if problems.is_empty() {
+            report.add("configuration", Status::Pass, detail);
+        } else {
+            report.add(
+                "configuration",
+                Status::Warn,
+                format!("{}: {}", problems.join(", "), detail),
+            );
+        }
+    }
+
+    fn check_cadence(&mut self, report: &mut DiagnosticReport, options: &DiagnosticOptions) {
+        // without a running measurement the waits below could only time out
+        if self.measuring_since.is_none() {
+            match self.data_available() {
+                Ok(true) => {}
+                Ok(false) => {
+                    report.add(
+                        "cadence",
+                        Status::Fail,
+                        "measurement not running".to_string(),
+                    );
+                    return;
+                }
+                Err(e) => {
+                    report.add(
+                        "cadence",
+                        Status::Fail,
+                        format!("cannot read data ready: {}", e),
+                    );
+                    return;
+                }
+            }
+        }
+        let interval = Duration::from_secs(u64::from(self.interval_in_s.max(1)));
+        let timeout = interval * 2 + Duration::from_secs(1);
+
+        // the first sample only synchronises to the sensor
+        let mut timestamps = Vec::with_capacity(options.cadence_samples + 1);
+        for _ in 0..=options.cadence_samples {
+            match self.wait_for_measure(timeout) {
+                Ok(_) => timestamps.push(self.clock.now()),
+                Err(e) => {
+                    report.add(
+                        "cadence",
+                        Status::Fail,
+                        format!(
+                            "no new sample after {} of {}: {}",
+                            timestamps.len(),
+                            options.cadence_samples + 1,
+                            e
+                        ),
+                    );
+                    return;
+                }
+            }
+        }
+        if timestamps.len() < 2 {
+            report.add("cadence", Status::Pass, "sample available".to_string());
+            return;
+        }
+        let period =
+            (timestamps[timestamps.len() - 1] - timestamps[0]) / (timestamps.len() - 1) as u32;
+        let deviation =
+            (period.as_secs_f32() - interval.as_secs_f32()).abs() / interval.as_secs_f32();
+        let detail = format!(
+            "sample every {:.1} s, interval {} s",
+            period.as_secs_f32(),
+            interval.as_secs()
+        );
+        if deviation <= options.cadence_tolerance {
+            report.add("cadence", Status::Pass, detail);
+        } else {
+            report.add("cadence", Status::Warn, detail);
+        }
+    }
+
+    fn check_crc_rate(&mut self, report: &mut DiagnosticReport, options: &DiagnosticOptions) {
+        if options.burst_reads == 0 {
+            return;
+        }
+        // the counters include failed retries which a plain error count would hide
+        let before = self.stats;
+        // the reads are spread a little to not hog a shared bus
+        for _ in 0..options.burst_reads {
+            self.clock.sleep(CMD_RESPONSE_DELAY);
+            let _ = self.read_u16_with_crc(CMD_GET_DATA_READY);
+        }
+        let crc_errors = self.stats.crc_failures - before.crc_failures;
+        let other_errors = self.stats.errors() - before.errors() - crc_errors;
+        let reads = self.stats.commands - before.commands;
+        let rate = (crc_errors + other_errors) as f32 / reads as f32;
+        let detail = format!(
+            "{} CRC errors and {} other errors in {} reads",
+            crc_errors, other_errors, reads
+        );
+        let status = if rate == 0.0 {
+            Status::Pass
+        } else if rate <= options.max_warn_error_rate {
+            Status::Warn
+        } else {
+            Status::Fail
+        };
+        report.add("crc", status, detail);
+    }
+
+    fn check_plausibility(&mut self, report: &mut DiagnosticReport) {
+        if self.last_read_time.is_none() {
+            report.add("plausibility", Status::Warn, "no measurement".to_string());
+            return;
+        }
+        let detail = format!(
+            "co2 = {:.0} ppm, temp = {:.2} °C, humidity = {:.0} %",
+            self.co2, self.temperature, self.humidity
+        );
+        let mut problems = Vec::new();
+        if self.co2 < 350.0 {
+            problems.push("CO2 below outdoor level, recalibration needed");
+        }
+        if self.co2 > 10_000.0 {
+            problems.push("CO2 unusually high");
+        }
+        if !(-10.0..=50.0).contains(&self.temperature) {
+            problems.push("temperature unusual for indoor air");
+        }
+        if self.humidity <= 0.0 || self.humidity >= 100.0 {
+            problems.push("humidity at the limit");
+        }
+        if problems.is_empty() {
+            report.add("plausibility", Status::Pass, detail);
+        } else {
+            report.add(
+                "plausibility",
+                Status::Warn,
+                format!("{}: {}", problems.join(", "), detail),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
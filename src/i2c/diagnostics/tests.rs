@@ -0,0 +1,174 @@
+/*
+MIT License
+
+Copyright (c) 2021 Crispin Tschirky <ct@fhr.ch>
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+ */
+
+use crate::clock::Clock;
+use crate::i2c::diagnostics::{DiagnosticOptions, Status};
+use crate::i2c::sim::testing::simulated_sensor;
+use crate::i2c::sim::Fault;
+use std::time::Duration;
+
+#[test]
+fn test_diagnose_healthy_sensor() {
+    let (mut sensor, sim, _) = simulated_sensor();
+    sim.set_environment(600.0, 22.0, 45.0);
+    sensor.start().unwrap();
+
+    let report = sensor.diagnose();
+    assert_eq!(Status::Pass, report.status(), "{}", report);
+    let checks: Vec<_> = report.entries.iter().map(|e| e.check).collect();
+    assert_eq!(
+        vec![
+            "bus",
+            "firmware",
+            "configuration",
+            "cadence",
+            "crc",
+            "plausibility"
+        ],
+        checks
+    );
+}
+
+#[test]
+fn test_diagnose_unreachable_sensor() {
+    let (mut sensor, sim, _) = simulated_sensor();
+    sim.inject_fault(Fault::Vanish, None);
+
+    let report = sensor.diagnose();
+    assert_eq!(Status::Fail, report.status());
+    assert_eq!(1, report.entries.len());
+    assert_eq!(Status::Fail, report.entry("bus").unwrap().status);
+}
+
+#[test]
+fn test_diagnose_stopped_sensor() {
+    let (mut sensor, _, _) = simulated_sensor();
+
+    let report = sensor.diagnose();
+    assert_eq!(Status::Fail, report.entry("cadence").unwrap().status);
+    assert_eq!(Status::Warn, report.entry("plausibility").unwrap().status);
+    assert_eq!(Status::Pass, report.entry("crc").unwrap().status);
+}
+
+#[test]
+fn test_diagnose_reports_measurement_not_running() {
+    let (mut sensor, _, clock) = simulated_sensor();
+    let start = clock.now();
+
+    let report = sensor.diagnose();
+    let entry = report.entry("cadence").unwrap();
+    assert_eq!(Status::Fail, entry.status);
+    assert_eq!("measurement not running", entry.detail);
+    // no time is spent waiting for samples which never come, only the CRC burst takes a while
+    assert!(clock.now() - start < Duration::from_secs(1));
+}
+
+#[test]
+fn test_diagnose_stuck_data_ready() {
+    let (mut sensor, sim, _) = simulated_sensor();
+    sensor.start().unwrap();
+    sim.inject_fault(Fault::StuckDataReady(false), None);
+
+    let report = sensor.diagnose();
+    assert_eq!(Status::Fail, report.entry("cadence").unwrap().status);
+}
+
+#[test]
+fn test_diagnose_crc_error_rate() {
+    let options = DiagnosticOptions {
+        cadence_samples: 0,
+        ..DiagnosticOptions::default()
+    };
+
+    // the cadence check waits for the first sample 2 s after the start, the faults only hit the
+    // burst which follows
+    let (mut sensor, sim, _) = simulated_sensor();
+    sensor.start().unwrap();
+    sim.schedule_fault(Duration::from_millis(2001), Fault::CorruptCrc, Some(1));
+    let report = sensor.diagnose_with(&options);
+    let entry = report.entry("crc").unwrap();
+    assert_eq!(Status::Warn, entry.status, "{}", report);
+    assert_eq!("1 CRC errors and 0 other errors in 20 reads", entry.detail);
+
+    let (mut sensor, sim, _) = simulated_sensor();
+    sensor.start().unwrap();
+    sim.schedule_fault(Duration::from_millis(2001), Fault::CorruptCrc, None);
+    let report = sensor.diagnose_with(&options);
+    assert_eq!(
+        Status::Fail,
+        report.entry("crc").unwrap().status,
+        "{}",
+        report
+    );
+}
+
+#[test]
+fn test_diagnose_implausible_values() {
+    let (mut sensor, sim, _) = simulated_sensor();
+    sim.set_environment(300.0, 22.0, 45.0);
+    sensor.start().unwrap();
+
+    let report = sensor.diagnose();
+    let entry = report.entry("plausibility").unwrap();
+    assert_eq!(Status::Warn, entry.status);
+    assert!(entry.detail.contains("recalibration"));
+    assert_eq!(Status::Warn, report.status());
+}
+
+#[test]
+fn test_diagnose_suspicious_configuration() {
+    let (mut sensor, sim, _) = simulated_sensor();
+    sensor.set_altitude_compensation(6000).unwrap();
+    sensor.start().unwrap();
+    sim.set_environment(600.0, 22.0, 45.0);
+
+    let report = sensor.diagnose();
+    assert_eq!(Status::Warn, report.entry("configuration").unwrap().status);
+}
+
+#[test]
+fn test_report_display() {
+    let (mut sensor, sim, _) = simulated_sensor();
+    sim.inject_fault(Fault::Vanish, None);
+
+    let text = sensor.diagnose().to_string();
+    assert!(text.starts_with("FAIL bus: sensor not reachable"));
+    assert!(text.ends_with("overall: FAIL"));
+}
+
+#[test]
+fn test_diagnose_counts_retried_errors() {
+    let (mut sensor, sim, _) = simulated_sensor();
+    sensor.set_retries(1);
+    sensor.start().unwrap();
+    sim.schedule_fault(Duration::from_millis(2001), Fault::CorruptCrc, Some(1));
+
+    let report = sensor.diagnose_with(&DiagnosticOptions {
+        cadence_samples: 0,
+        ..DiagnosticOptions::default()
+    });
+    let entry = report.entry("crc").unwrap();
+    assert_eq!(Status::Warn, entry.status);
+    assert_eq!("1 CRC errors and 0 other errors in 21 reads", entry.detail);
+}
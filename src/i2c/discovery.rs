@@ -0,0 +1,147 @@
+/*
+MIT License
+
+Copyright (c) 2021 Crispin Tschirky <ct@fhr.ch>
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+ */
+
+//! Scans the I2C buses of the host for SCD30 sensors.
+//!
+//! A candidate address is probed by requesting the firmware version. Only devices answering with a
+//! CRC protected word are reported, other devices on the bus are ignored.
+//!
+//! ```no_run
+//! use scd30pi::i2c::discovery;
+//!
+//! for found in discovery::scan().unwrap() {
+//!     println!("SCD30 on bus {} at {:#x}, firmware {}", found.bus, found.address, found.firmware_version);
+//!     let sensor = found.open().unwrap();
+//! }
+//! ```
+
+use crate::i2c::{
+    decode_word, format_firmware_version, prepare_cmd, Error, Transport, CMD_GET_FIRMWARE_VERSION,
+    CMD_RESPONSE_DELAY, DEFAULT_SLAVE_ADDRESS, SCD30,
+};
+use log::debug;
+use rppal::i2c::I2c;
+use std::fs;
+
+/// Directory containing the I2C bus device nodes
+const DEVICE_DIR: &str = "/dev";
+
+/// A sensor found on an I2C bus.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DetectedSensor {
+    /// I2C bus number as in `/dev/i2c-<bus>`
+    pub bus: u8,
+    /// I2C slave address
+    pub address: u16,
+    /// firmware version reported by the sensor
+    pub firmware_version: String,
+}
+
+impl DetectedSensor {
+    /// Opens the detected sensor.
+    pub fn open(&self) -> Result<SCD30, Error> {
+        SCD30::from_bus(self.bus, self.address)
+    }
+}
+
+/// Lists the I2C bus numbers available on the host, sorted ascending.
+pub fn list_buses() -> Result<Vec<u8>, Error> {
+    let mut buses = Vec::new();
+    for entry in fs::read_dir(DEVICE_DIR)? {
+        let entry = entry?;
+        if let Some(bus) = entry.file_name().to_str().and_then(parse_bus_name) {
+            buses.push(bus);
+        }
+    }
+    buses.sort_unstable();
+    Ok(buses)
+}
+
+/// Scans all I2C buses of the host for sensors at the default address.
+pub fn scan() -> Result<Vec<DetectedSensor>, Error> {
+    let buses = list_buses()?;
+    Ok(scan_buses(&buses, &[DEFAULT_SLAVE_ADDRESS]))
+}
+
+/// Scans the given buses for sensors at the given candidate addresses. Buses which cannot be
+/// opened and addresses not answering like an SCD30 are skipped.
+pub fn scan_buses(buses: &[u8], addresses: &[u16]) -> Vec<DetectedSensor> {
+    let mut found = Vec::new();
+    for &bus in buses {
+        match I2c::with_bus(bus) {
+            Ok(mut i2c) => found.extend(scan_bus(&mut i2c, bus, addresses)),
+            Err(e) => debug!("Skipping bus {}: {}", bus, e),
+        }
+    }
+    found
+}
+
+/// Scans a bus for sensors at the given candidate addresses. The bus is reported as the given
+/// number and must support [`Transport::set_slave_address`].
+pub fn scan_bus<T: Transport>(
+    transport: &mut T,
+    bus: u8,
+    addresses: &[u16],
+) -> Vec<DetectedSensor> {
+    let mut found = Vec::new();
+    for &address in addresses {
+        match probe(transport, address) {
+            Ok(firmware_version) => {
+                debug!("Found SCD30 on bus {} at {:#x}", bus, address);
+                found.push(DetectedSensor {
+                    bus,
+                    address,
+                    firmware_version,
+                });
+            }
+            Err(e) => debug!("No SCD30 on bus {} at {:#x}: {}", bus, address, e),
+        }
+    }
+    found
+}
+
+/// Probes an address by reading the firmware version and returns it on success.
+fn probe<T: Transport>(transport: &mut T, address: u16) -> Result<String, Error> {
+    transport.set_slave_address(address)?;
+    let mut rcv_buf = [0u8; 3];
+    let count = transport.write_read(
+        &prepare_cmd(CMD_GET_FIRMWARE_VERSION),
+        CMD_RESPONSE_DELAY,
+        &mut rcv_buf,
+    )?;
+    let version = decode_word(&rcv_buf[..count])?;
+    Ok(format_firmware_version(version))
+}
+
+/// Extracts the bus number from a device node name such as `i2c-1`.
+pub(crate) fn parse_bus_name(name: &str) -> Option<u8> {
+    if let Some(stripped) = name.strip_prefix("i2c-") {
+        stripped.parse::<u8>().ok()
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests;
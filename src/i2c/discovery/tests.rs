@@ -0,0 +1,123 @@
+/*
+MIT License
+
+Copyright (c) 2021 Crispin Tschirky <ct@fhr.ch>
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+ */
+
+use crate::clock::ManualClock;
+use crate::i2c::discovery::{parse_bus_name, scan_bus, DetectedSensor};
+use crate::i2c::sim::{Fault, SimulatedSCD30};
+use crate::i2c::{Error, Transport, DEFAULT_SLAVE_ADDRESS};
+use std::io;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// errno returned by the Linux i2c-dev driver if the slave does not acknowledge
+const EREMOTEIO: i32 = 121;
+
+/// Bus with a simulated sensor at the default address and nothing else
+struct SimulatedBus {
+    sim: SimulatedSCD30,
+    slave_address: u16,
+}
+
+impl SimulatedBus {
+    fn new() -> SimulatedBus {
+        SimulatedBus {
+            sim: SimulatedSCD30::new(Arc::new(ManualClock::new())),
+            slave_address: 0,
+        }
+    }
+
+    /// Gets the addressed device, only the sensor acknowledges
+    fn device(&mut self) -> Result<&mut SimulatedSCD30, Error> {
+        if self.slave_address == DEFAULT_SLAVE_ADDRESS {
+            Ok(&mut self.sim)
+        } else {
+            Err(Error::Io(io::Error::from_raw_os_error(EREMOTEIO)))
+        }
+    }
+}
+
+impl Transport for SimulatedBus {
+    fn write(&mut self, buf: &[u8]) -> Result<(), Error> {
+        self.device()?.write(buf)
+    }
+
+    fn write_read(
+        &mut self,
+        buf: &[u8],
+        delay: Duration,
+        out_buf: &mut [u8],
+    ) -> Result<usize, Error> {
+        self.device()?.write_read(buf, delay, out_buf)
+    }
+
+    fn set_slave_address(&mut self, slave_address: u16) -> Result<(), Error> {
+        self.slave_address = slave_address;
+        Ok(())
+    }
+}
+
+#[test]
+fn test_parse_bus_name() {
+    assert_eq!(Some(1), parse_bus_name("i2c-1"));
+    assert_eq!(Some(22), parse_bus_name("i2c-22"));
+}
+
+#[test]
+fn test_parse_bus_name_invalid() {
+    assert_eq!(None, parse_bus_name("i2c-"));
+    assert_eq!(None, parse_bus_name("i2c-dev"));
+    assert_eq!(None, parse_bus_name("spidev0.0"));
+    assert_eq!(None, parse_bus_name("i2c-300"));
+}
+
+#[test]
+fn test_scan_bus_detects_sensor() {
+    let mut bus = SimulatedBus::new();
+    let found = scan_bus(&mut bus, 1, &[DEFAULT_SLAVE_ADDRESS]);
+    assert_eq!(
+        vec![DetectedSensor {
+            bus: 1,
+            address: DEFAULT_SLAVE_ADDRESS,
+            firmware_version: "3.66".to_string(),
+        }],
+        found
+    );
+}
+
+#[test]
+fn test_scan_bus_skips_absent_address() {
+    let mut bus = SimulatedBus::new();
+    let found = scan_bus(&mut bus, 1, &[0x60, DEFAULT_SLAVE_ADDRESS, 0x62]);
+    assert_eq!(1, found.len());
+    assert_eq!(DEFAULT_SLAVE_ADDRESS, found[0].address);
+    assert!(scan_bus(&mut bus, 1, &[0x60, 0x62]).is_empty());
+}
+
+#[test]
+fn test_scan_bus_rejects_crc_error() {
+    let mut bus = SimulatedBus::new();
+    bus.sim.inject_fault(Fault::CorruptCrc, Some(1));
+    assert!(scan_bus(&mut bus, 1, &[DEFAULT_SLAVE_ADDRESS]).is_empty());
+    assert_eq!(1, scan_bus(&mut bus, 1, &[DEFAULT_SLAVE_ADDRESS]).len());
+}
@@ -0,0 +1,169 @@
+/*
+MIT License
+
+Copyright (c) 2021 Crispin Tschirky <ct@fhr.ch>
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+ */
+
+//! Duty-cycled measurement for battery powered deployments.
+//!
+//! Instead of measuring continuously the sensor is started for a short burst: after the warm-up
+//! the first unstable samples are discarded, a number of samples is averaged and the sensor is
+//! stopped again until the next wake-up.
+//!
+//! ```no_run
+//! use scd30pi::i2c::duty::DutyCycle;
+//! use scd30pi::i2c::SCD30;
+//! use std::time::Duration;
+//!
+//! let mut sensor = SCD30::new().unwrap();
+//! let cycle = DutyCycle::default();
+//! sensor.run_duty_cycled(&cycle, Duration::from_secs(600), |result| {
+//!     println!("{:?}", result);
+//!     true
+//! });
+//! ```
+
+use crate::i2c::{Error, Measurement, Transport, MEASURE_INTERVAL_RANGE, SCD30};
+use log::{debug, warn};
+use std::time::Duration;
+
+/// Parameters of a duty-cycled measurement.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DutyCycle {
+    /// measure interval in seconds while the sensor is running
+    pub measure_interval: u16,
+    /// time to wait after the start before samples are read, the sensor needs up to 10s. The
+    /// result is flagged as stabilizing if samples are taken within the stabilization time of the
+    /// sensor, see [`SCD30::set_stabilization_time`].
+    pub warm_up: Duration,
+    /// number of samples discarded after the warm-up
+    pub discard: usize,
+    /// number of samples averaged to the result
+    pub samples: usize,
+}
+
+impl Default for DutyCycle {
+    fn default() -> Self {
+        DutyCycle {
+            measure_interval: 2,
+            warm_up: Duration::from_secs(10),
+            discard: 2,
+            samples: 5,
+        }
+    }
+}
+
+impl DutyCycle {
+    /// Gets the time the sensor is running per cycle if no sample is late
+    pub fn awake_time(&self) -> Duration {
+        self.warm_up
+            + Duration::from_secs(u64::from(self.measure_interval))
+                * (self.discard + self.samples) as u32
+    }
+}
+
+impl<T: Transport> SCD30<T> {
+    /// Starts the measurement, waits for the sensor to stabilise, averages the configured number
+    /// of samples and stops the sensor again. The sensor is stopped even if reading fails. A
+    /// pressure compensation given with [`SCD30::start_with_alt_comp`] is kept.
+    pub fn measure_duty_cycle(&mut self, cycle: &DutyCycle) -> Result<Measurement, Error> {
+        if cycle.samples == 0 {
+            return Err(Error::Config(
+                "duty cycle needs at least one sample".to_string(),
+            ));
+        }
+        if !MEASURE_INTERVAL_RANGE.contains(&cycle.measure_interval) {
+            return Err(Error::Config(format!(
+                "duty cycle measure_interval {} out of range {}..{}",
+                cycle.measure_interval,
+                MEASURE_INTERVAL_RANGE.start(),
+                MEASURE_INTERVAL_RANGE.end()
+            )));
+        }
+        if self.interval_in_s != cycle.measure_interval {
+            self.set_measure_interval(cycle.measure_interval)?;
+        }
+        let pressure_mbar = self.pressure_compensation();
+        self.start_with_alt_comp(pressure_mbar)?;
+
+        let res = self.sample_after_warm_up(cycle);
+        if let Err(e) = self.stop() {
+            warn!("Cannot stop sensor after duty cycle: {}", e);
+            if res.is_ok() {
+                return Err(e);
+            }
+        }
+        res
+    }
+
+    /// Measures with the given duty cycle every `period` and passes the results to `f` until it
+    /// returns false. The sensor is stopped between the measurements.
+    pub fn run_duty_cycled<F>(&mut self, cycle: &DutyCycle, period: Duration, mut f: F)
+    where
+        F: FnMut(Result<Measurement, Error>) -> bool,
+    {
+        loop {
+            let started = self.clock.now();
+            if !f(self.measure_duty_cycle(cycle)) {
+                return;
+            }
+            if let Some(remaining) = period.checked_sub(self.clock.now() - started) {
+                self.clock.sleep(remaining);
+            }
+        }
+    }
+
+    fn sample_after_warm_up(&mut self, cycle: &DutyCycle) -> Result<Measurement, Error> {
+        self.clock.sleep(cycle.warm_up);
+        let timeout = Duration::from_secs(2 * u64::from(cycle.measure_interval));
+
+        // discard the sample taken during the warm-up and the unstable ones after it
+        let _ = self.read_measure_if_available()?;
+        for _ in 0..cycle.discard {
+            self.wait_for_measure(timeout)?;
+        }
+
+        let (mut co2, mut temperature, mut humidity) = (0f32, 0f32, 0f32);
+        let mut stabilizing = false;
+        for _ in 0..cycle.samples {
+            self.wait_for_measure(timeout)?;
+            stabilizing |= self.is_stabilizing();
+            co2 += self.co2;
+            temperature += self.temperature;
+            humidity += self.humidity;
+        }
+        let n = cycle.samples as f32;
+        let timestamp = self.sample_time.unwrap_or_else(|| self.clock.now());
+        let measurement = Measurement {
+            co2: co2 / n,
+            temperature: temperature / n,
+            humidity: humidity / n,
+            timestamp,
+            age: self.clock.now() - timestamp,
+            stabilizing,
+        };
+        debug!("Duty cycle measurement {:?}", measurement);
+        Ok(measurement)
+    }
+}
+
+#[cfg(test)]
+mod tests;
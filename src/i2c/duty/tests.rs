@@ -0,0 +1,152 @@
+/*
+MIT License
+
+Copyright (c) 2021 Crispin Tschirky <ct@fhr.ch>
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+ */
+
+use crate::clock::Clock;
+use crate::i2c::duty::DutyCycle;
+use crate::i2c::sim::testing::simulated_sensor;
+use crate::i2c::sim::{Fault, Profile, Scenario};
+use crate::i2c::Error;
+use std::time::Duration;
+
+#[test]
+fn test_duty_cycle_averages_stable_samples() {
+    let (mut sensor, sim, clock) = simulated_sensor();
+    // rises by 10 ppm per second
+    sim.run_scenario(Scenario::new(
+        Profile::constant(400.0).ramp(1400.0, Duration::from_secs(100)),
+        Profile::constant(21.0),
+        Profile::constant(40.0),
+    ));
+    let start = clock.now();
+
+    let measurement = sensor.measure_duty_cycle(&DutyCycle::default()).unwrap();
+    // samples at 16, 18, 20, 22 and 24 s are averaged
+    assert!((measurement.co2 - 600.0).abs() < 0.01);
+    assert_eq!(21.0, measurement.temperature);
+    assert_eq!(40.0, measurement.humidity);
+    assert_eq!(Duration::from_secs(24), measurement.timestamp - start);
+    assert!(!measurement.stabilizing);
+    assert_eq!(None, sim.measuring());
+}
+
+#[test]
+fn test_duty_cycle_keeps_pressure_compensation() {
+    let (mut sensor, sim, _) = simulated_sensor();
+    sensor.start_with_alt_comp(950).unwrap();
+    sensor.stop().unwrap();
+    let cycle = DutyCycle {
+        measure_interval: 5,
+        warm_up: Duration::from_secs(10),
+        discard: 0,
+        samples: 1,
+    };
+    sensor.measure_duty_cycle(&cycle).unwrap();
+    assert_eq!(950, sensor.pressure_compensation());
+    assert_eq!(5, sim.measure_interval());
+}
+
+#[test]
+fn test_duty_cycle_stops_sensor_on_error() {
+    let (mut sensor, sim, _) = simulated_sensor();
+    sim.schedule_fault(Duration::from_secs(5), Fault::StuckDataReady(false), None);
+    assert!(sensor.measure_duty_cycle(&DutyCycle::default()).is_err());
+    assert_eq!(None, sim.measuring());
+}
+
+#[test]
+fn test_duty_cycle_needs_samples() {
+    let (mut sensor, _, _) = simulated_sensor();
+    let cycle = DutyCycle {
+        samples: 0,
+        ..DutyCycle::default()
+    };
+    assert!(sensor.measure_duty_cycle(&cycle).is_err());
+}
+
+#[test]
+fn test_duty_cycle_rejects_invalid_interval() {
+    let (mut sensor, sim, _) = simulated_sensor();
+    for measure_interval in [0, 1, 1801] {
+        let cycle = DutyCycle {
+            measure_interval,
+            ..DutyCycle::default()
+        };
+        match sensor.measure_duty_cycle(&cycle) {
+            Err(Error::Config(_)) => {}
+            other => panic!("unexpected result {:?} for {}", other, measure_interval),
+        }
+    }
+    assert_eq!(None, sim.measuring());
+    assert_eq!(2, sim.measure_interval());
+}
+
+#[test]
+fn test_set_measure_interval_keeps_cache_on_failure() {
+    let (mut sensor, sim, _) = simulated_sensor();
+    assert!(matches!(
+        sensor.set_measure_interval(1),
+        Err(Error::Config(_))
+    ));
+    sim.inject_fault(Fault::Nack, Some(1));
+    assert!(sensor.set_measure_interval(10).is_err());
+    assert_eq!(2, sensor.interval_in_s);
+    assert_eq!(2, sim.measure_interval());
+
+    sensor.set_measure_interval(10).unwrap();
+    assert_eq!(10, sensor.interval_in_s);
+}
+
+#[test]
+fn test_run_duty_cycled_period() {
+    let (mut sensor, sim, clock) = simulated_sensor();
+    let start = clock.now();
+    let mut wake_ups = Vec::new();
+    sensor.run_duty_cycled(&DutyCycle::default(), Duration::from_secs(600), |result| {
+        assert!(result.is_ok());
+        assert_eq!(None, sim.measuring());
+        wake_ups.push(clock.now() - start);
+        wake_ups.len() < 3
+    });
+    assert_eq!(
+        vec![
+            Duration::from_secs(24),
+            Duration::from_secs(624),
+            Duration::from_secs(1224)
+        ],
+        wake_ups
+    );
+}
+
+#[test]
+fn test_duty_cycle_awake_time() {
+    assert_eq!(Duration::from_secs(24), DutyCycle::default().awake_time());
+}
+
+#[test]
+fn test_duty_cycle_flags_samples_within_stabilization_time() {
+    let (mut sensor, _, _) = simulated_sensor();
+    sensor.set_stabilization_time(Duration::from_secs(60));
+    let measurement = sensor.measure_duty_cycle(&DutyCycle::default()).unwrap();
+    assert!(measurement.stabilizing);
+}
@@ -0,0 +1,108 @@
+/*
+MIT License
+
+Copyright (c) 2021 Crispin Tschirky <ct@fhr.ch>
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+ */
+
+//! Optional `tracing` instrumentation of the bus transactions.
+//!
+//! With the `tracing` feature every command sent to the sensor is wrapped in a `scd30_command`
+//! span with the fields `command`, `name`, `args`, `latency_us`, `bytes`, `attempts` and
+//! `outcome`. Without the feature the span is a no-op.
+
+use crate::i2c::Error;
+use std::time::Instant;
+
+#[cfg(feature = "tracing")]
+use crate::i2c::command_name;
+
+/// Span of a single command including its retries
+pub(crate) struct CommandSpan {
+    #[cfg(feature = "tracing")]
+    span: tracing::span::EnteredSpan,
+    #[cfg(feature = "tracing")]
+    started: Instant,
+}
+
+#[cfg(feature = "tracing")]
+impl CommandSpan {
+    /// Opens and enters the span of the command in the given buffer
+    pub(crate) fn enter(buf: &[u8], now: Instant) -> CommandSpan {
+        let command = match buf {
+            [high, low, ..] => u16::from_be_bytes([*high, *low]),
+            _ => 0,
+        };
+        let span = tracing::debug_span!(
+            "scd30_command",
+            command = command,
+            name = command_name(command).unwrap_or("unknown"),
+            args = tracing::field::Empty,
+            latency_us = tracing::field::Empty,
+            bytes = tracing::field::Empty,
+            attempts = tracing::field::Empty,
+            outcome = tracing::field::Empty,
+        );
+        if let [_, _, high, low, _] = buf {
+            span.record("args", u16::from_be_bytes([*high, *low]));
+        }
+        CommandSpan {
+            span: span.entered(),
+            started: now,
+        }
+    }
+
+    /// Records the outcome of the command and closes the span
+    pub(crate) fn finish<R>(
+        self,
+        res: &Result<R, Error>,
+        bytes: usize,
+        attempts: u16,
+        now: Instant,
+    ) {
+        let latency = now.saturating_duration_since(self.started);
+        self.span.record("latency_us", latency.as_micros() as u64);
+        self.span.record("bytes", bytes as u64);
+        self.span.record("attempts", attempts);
+        match res {
+            Ok(_) => self.span.record("outcome", "ok"),
+            Err(e) => self.span.record("outcome", tracing::field::display(e)),
+        };
+    }
+}
+
+#[cfg(not(feature = "tracing"))]
+impl CommandSpan {
+    pub(crate) fn enter(_buf: &[u8], _now: Instant) -> CommandSpan {
+        CommandSpan {}
+    }
+
+    pub(crate) fn finish<R>(
+        self,
+        _res: &Result<R, Error>,
+        _bytes: usize,
+        _attempts: u16,
+        _now: Instant,
+    ) {
+    }
+}
+
+#[cfg(all(test, feature = "tracing"))]
+mod tests;
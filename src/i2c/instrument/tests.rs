@@ -0,0 +1,144 @@
+/*
+MIT License
+
+Copyright (c) 2021 Crispin Tschirky <ct@fhr.ch>
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+ */
+
+use crate::i2c::sim::testing::simulated_sensor;
+use crate::i2c::sim::Fault;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Metadata, Subscriber};
+
+/// Fields of the spans recorded by the collector
+type Spans = Arc<Mutex<Vec<HashMap<String, String>>>>;
+
+struct Collector {
+    spans: Spans,
+}
+
+struct FieldVisitor<'a>(&'a mut HashMap<String, String>);
+
+impl Visit for FieldVisitor<'_> {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0
+            .insert(field.name().to_string(), format!("{:?}", value));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_string(), value.to_string());
+    }
+}
+
+impl Subscriber for Collector {
+    fn enabled(&self, _metadata: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, span: &Attributes<'_>) -> Id {
+        let mut fields = HashMap::new();
+        span.record(&mut FieldVisitor(&mut fields));
+        let mut spans = self.spans.lock().unwrap();
+        spans.push(fields);
+        Id::from_u64(spans.len() as u64)
+    }
+
+    fn record(&self, span: &Id, values: &Record<'_>) {
+        let mut spans = self.spans.lock().unwrap();
+        let fields = &mut spans[span.into_u64() as usize - 1];
+        values.record(&mut FieldVisitor(fields));
+    }
+
+    fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+    fn event(&self, _event: &Event<'_>) {}
+
+    fn enter(&self, _span: &Id) {}
+
+    fn exit(&self, _span: &Id) {}
+}
+
+fn collect<F: FnOnce()>(f: F) -> Vec<HashMap<String, String>> {
+    let spans = Spans::default();
+    let collector = Collector {
+        spans: Arc::clone(&spans),
+    };
+    tracing::subscriber::with_default(collector, f);
+    let spans = spans.lock().unwrap();
+    spans.clone()
+}
+
+#[test]
+fn test_span_per_command() {
+    let (mut sensor, _, _) = simulated_sensor();
+    let spans = collect(|| {
+        sensor.set_measure_interval(5).unwrap();
+        sensor.read_firmware_version().unwrap();
+    });
+
+    assert_eq!(2, spans.len());
+    let write = &spans[0];
+    assert_eq!("17920", write["command"]);
+    assert_eq!("measurement interval", write["name"]);
+    assert_eq!("5", write["args"]);
+    assert_eq!("5", write["bytes"]);
+    assert_eq!("1", write["attempts"]);
+    assert_eq!("ok", write["outcome"]);
+    assert_eq!("0", write["latency_us"]);
+
+    let read = &spans[1];
+    assert_eq!("read firmware version", read["name"]);
+    assert!(!read.contains_key("args"));
+    assert_eq!("3", read["bytes"]);
+}
+
+#[test]
+fn test_span_records_failure_and_retries() {
+    let (mut sensor, sim, _) = simulated_sensor();
+    sensor.set_retries(1);
+    sim.inject_fault(Fault::CorruptCrc, None);
+    let spans = collect(|| {
+        assert!(sensor.read_firmware_version().is_err());
+    });
+
+    assert_eq!(1, spans.len());
+    assert_eq!("2", spans[0]["attempts"]);
+    assert_eq!("10000", spans[0]["latency_us"]);
+    assert!(spans[0]["outcome"].starts_with("CrcError"));
+}
+
+#[test]
+fn test_span_counts_all_retries() {
+    let (mut sensor, sim, _) = simulated_sensor();
+    sensor.set_retries(255);
+    sim.inject_fault(Fault::Nack, None);
+    let spans = collect(|| {
+        assert!(sensor.set_measure_interval(5).is_err());
+        assert!(sensor.read_firmware_version().is_err());
+    });
+
+    assert_eq!(2, spans.len());
+    assert_eq!("256", spans[0]["attempts"]);
+    assert_eq!("256", spans[1]["attempts"]);
+}
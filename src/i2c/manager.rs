@@ -0,0 +1,268 @@
+/*
+MIT License
+
+Copyright (c) 2021 Crispin Tschirky <ct@fhr.ch>
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+ */
+
+//! Manages a fleet of sensors on one host.
+//!
+//! The [`SensorManager`] owns several named sensors, possibly on different buses, multiplexer
+//! channels or other transports. The sensors are read one after another from a single thread, so
+//! sensors sharing a bus never compete for it. A failing sensor is isolated: after a number of
+//! consecutive failures it is marked as failed and only retried after a delay, while the other
+//! sensors continue to be read.
+//!
+//! ```no_run
+//! use scd30pi::i2c::manager::SensorManager;
+//! use scd30pi::i2c::mux::{Tca9548a, DEFAULT_MUX_ADDRESS};
+//! use std::time::Duration;
+//!
+//! let mux = Tca9548a::new(1, DEFAULT_MUX_ADDRESS).unwrap();
+//! let mut manager = SensorManager::new();
+//! manager.add("kitchen", mux.sensor(0).unwrap()).unwrap();
+//! manager.add("office", mux.sensor(1).unwrap()).unwrap();
+//!
+//! manager.run(Duration::from_secs(2), |statuses| {
+//!     for status in statuses {
+//!         println!("{}: {:?} {:?}", status.name, status.health, status.measurement);
+//!     }
+//!     true
+//! });
+//! ```
+
+use crate::clock::{default_clock, Clock};
+use crate::i2c::{Error, Measurement, Transport, SCD30};
+use log::{debug, warn};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Sensor on an arbitrary transport as kept by the manager
+pub type ManagedSCD30 = SCD30<Box<dyn Transport + Send>>;
+
+/// Default number of consecutive failures after which a sensor is considered failed
+const DEFAULT_FAILURE_THRESHOLD: u32 = 3;
+/// Default delay before a failed sensor is read again
+const DEFAULT_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+/// Health state of a managed sensor.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Health {
+    /// The last read succeeded
+    Healthy,
+    /// The last reads failed, but the failure threshold is not reached yet
+    Degraded { consecutive_failures: u32 },
+    /// The failure threshold is reached, the sensor is not read before `retry_at`
+    Failed {
+        consecutive_failures: u32,
+        retry_at: Instant,
+    },
+}
+
+/// State of a managed sensor after a poll.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SensorStatus {
+    /// name the sensor was added with
+    pub name: String,
+    /// health state of the sensor
+    pub health: Health,
+    /// last successfully read measurement
+    pub measurement: Option<Measurement>,
+    /// description of the last error, cleared on success
+    pub last_error: Option<String>,
+}
+
+struct ManagedSensor {
+    sensor: ManagedSCD30,
+    status: SensorStatus,
+}
+
+/// Owns multiple named sensors and reads them without bus contention.
+pub struct SensorManager {
+    sensors: Vec<ManagedSensor>,
+    failure_threshold: u32,
+    retry_delay: Duration,
+    clock: Arc<dyn Clock>,
+}
+
+impl Default for SensorManager {
+    fn default() -> Self {
+        SensorManager::new()
+    }
+}
+
+impl SensorManager {
+    /// Creates an empty manager
+    pub fn new() -> SensorManager {
+        SensorManager {
+            sensors: Vec::new(),
+            failure_threshold: DEFAULT_FAILURE_THRESHOLD,
+            retry_delay: DEFAULT_RETRY_DELAY,
+            clock: default_clock(),
+        }
+    }
+
+    /// Sets the number of consecutive failures after which a sensor is considered failed.
+    pub fn set_failure_threshold(&mut self, failure_threshold: u32) {
+        self.failure_threshold = failure_threshold.max(1);
+    }
+
+    /// Sets the delay before a failed sensor is read again.
+    pub fn set_retry_delay(&mut self, retry_delay: Duration) {
+        self.retry_delay = retry_delay;
+    }
+
+    /// Replaces the time source used for retry delays and the poll period.
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.clock = clock;
+    }
+
+    /// Adds a sensor under the given name. Names must be unique.
+    pub fn add<T: Transport + Send + 'static>(
+        &mut self,
+        name: &str,
+        sensor: SCD30<T>,
+    ) -> Result<(), Error> {
+        if self.find(name).is_some() {
+            return Err(Error::Config(format!("sensor '{}' already exists", name)));
+        }
+        self.sensors.push(ManagedSensor {
+            sensor: sensor.boxed(),
+            status: SensorStatus {
+                name: name.to_string(),
+                health: Health::Healthy,
+                measurement: None,
+                last_error: None,
+            },
+        });
+        Ok(())
+    }
+
+    /// Removes the sensor with the given name and hands it back.
+    pub fn remove(&mut self, name: &str) -> Option<ManagedSCD30> {
+        self.find(name).map(|idx| self.sensors.remove(idx).sensor)
+    }
+
+    /// Gets the names of all sensors in the order they were added.
+    pub fn names(&self) -> Vec<&str> {
+        self.sensors
+            .iter()
+            .map(|s| s.status.name.as_str())
+            .collect()
+    }
+
+    /// Gives access to a sensor, e.g. to change its configuration.
+    pub fn sensor_mut(&mut self, name: &str) -> Option<&mut ManagedSCD30> {
+        match self.find(name) {
+            Some(idx) => Some(&mut self.sensors[idx].sensor),
+            None => None,
+        }
+    }
+
+    /// Gets the status of the sensor with the given name as of the last poll.
+    pub fn status(&self, name: &str) -> Option<&SensorStatus> {
+        self.find(name).map(|idx| &self.sensors[idx].status)
+    }
+
+    /// Gets the status of all sensors as of the last poll.
+    pub fn statuses(&self) -> Vec<SensorStatus> {
+        self.sensors.iter().map(|s| s.status.clone()).collect()
+    }
+
+    /// Reads all sensors one after another and returns their status. Failed sensors are skipped
+    /// until their retry time is reached.
+    pub fn poll(&mut self) -> Vec<SensorStatus> {
+        let now = self.clock.now();
+        for managed in self.sensors.iter_mut() {
+            if let Health::Failed { retry_at, .. } = managed.status.health {
+                if now < retry_at {
+                    continue;
+                }
+            }
+            match managed.sensor.measurement() {
+                Ok(measurement) => {
+                    if measurement.is_some() {
+                        managed.status.measurement = measurement;
+                    }
+                    managed.status.health = Health::Healthy;
+                    managed.status.last_error = None;
+                }
+                Err(e) => {
+                    let consecutive_failures = failures(managed.status.health) + 1;
+                    managed.status.health = if consecutive_failures >= self.failure_threshold {
+                        warn!(
+                            "Sensor {} failed {} times: {}",
+                            managed.status.name, consecutive_failures, e
+                        );
+                        Health::Failed {
+                            consecutive_failures,
+                            retry_at: now + self.retry_delay,
+                        }
+                    } else {
+                        debug!("Sensor {} failed: {}", managed.status.name, e);
+                        Health::Degraded {
+                            consecutive_failures,
+                        }
+                    };
+                    managed.status.last_error = Some(e.to_string());
+                }
+            }
+        }
+        self.statuses()
+    }
+
+    /// Polls all sensors every `period` and passes the result to `f` until it returns false.
+    pub fn run<F>(&mut self, period: Duration, mut f: F)
+    where
+        F: FnMut(&[SensorStatus]) -> bool,
+    {
+        loop {
+            let started = self.clock.now();
+            let statuses = self.poll();
+            if !f(&statuses) {
+                return;
+            }
+            if let Some(remaining) = period.checked_sub(self.clock.now() - started) {
+                self.clock.sleep(remaining);
+            }
+        }
+    }
+
+    fn find(&self, name: &str) -> Option<usize> {
+        self.sensors.iter().position(|s| s.status.name == name)
+    }
+}
+
+/// Gets the number of consecutive failures of a health state.
+fn failures(health: Health) -> u32 {
+    match health {
+        Health::Healthy => 0,
+        Health::Degraded {
+            consecutive_failures,
+        }
+        | Health::Failed {
+            consecutive_failures,
+            ..
+        } => consecutive_failures,
+    }
+}
+
+#[cfg(test)]
+mod tests;
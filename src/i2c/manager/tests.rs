@@ -0,0 +1,191 @@
+/*
+MIT License
+
+Copyright (c) 2021 Crispin Tschirky <ct@fhr.ch>
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+ */
+
+use crate::clock::{Clock, ManualClock};
+use crate::i2c::manager::{Health, SensorManager};
+use crate::i2c::{calculate_crc8, Error, Transport, SCD30};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Transport answering like a sensor with a fixed measurement which can be switched to fail.
+struct StubTransport {
+    failing: Arc<AtomicBool>,
+}
+
+fn push_word(buf: &mut Vec<u8>, word: [u8; 2]) {
+    buf.extend_from_slice(&word);
+    buf.push(calculate_crc8(&word));
+}
+
+impl Transport for StubTransport {
+    fn write(&mut self, _buf: &[u8]) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn write_read(
+        &mut self,
+        buf: &[u8],
+        _delay: Duration,
+        out_buf: &mut [u8],
+    ) -> Result<usize, Error> {
+        if self.failing.load(Ordering::SeqCst) {
+            return Err(Error::NoData("No data read".to_string()));
+        }
+        let mut response = Vec::new();
+        match (buf[0], buf[1]) {
+            (0x46, 0x00) => push_word(&mut response, [0x00, 0x02]),
+            (0x02, 0x02) => push_word(&mut response, [0x00, 0x01]),
+            (0x03, 0x00) => {
+                for value in &[800f32, 21.5, 45.0] {
+                    let bytes = value.to_bits().to_be_bytes();
+                    push_word(&mut response, [bytes[0], bytes[1]]);
+                    push_word(&mut response, [bytes[2], bytes[3]]);
+                }
+            }
+            _ => return Err(Error::NotImplemented),
+        }
+        out_buf[..response.len()].copy_from_slice(&response);
+        Ok(response.len())
+    }
+}
+
+fn stub_sensor() -> (SCD30<StubTransport>, Arc<AtomicBool>) {
+    let failing = Arc::new(AtomicBool::new(false));
+    let transport = StubTransport {
+        failing: Arc::clone(&failing),
+    };
+    (SCD30::from_transport(transport).unwrap(), failing)
+}
+
+#[test]
+fn test_manager_duplicate_name() {
+    let mut manager = SensorManager::new();
+    manager.add("kitchen", stub_sensor().0).unwrap();
+    assert!(manager.add("kitchen", stub_sensor().0).is_err());
+    assert_eq!(vec!["kitchen"], manager.names());
+}
+
+#[test]
+fn test_manager_poll_healthy() {
+    let mut manager = SensorManager::new();
+    manager.add("kitchen", stub_sensor().0).unwrap();
+    manager.add("office", stub_sensor().0).unwrap();
+
+    let statuses = manager.poll();
+    assert_eq!(2, statuses.len());
+    for status in statuses {
+        assert_eq!(Health::Healthy, status.health);
+        assert_eq!(800f32, status.measurement.unwrap().co2);
+    }
+}
+
+#[test]
+fn test_manager_isolates_failures() {
+    let mut manager = SensorManager::new();
+    manager.set_failure_threshold(2);
+    manager.set_retry_delay(Duration::from_secs(3600));
+    let (broken, failing) = stub_sensor();
+    manager.add("broken", broken).unwrap();
+    manager.add("office", stub_sensor().0).unwrap();
+    failing.store(true, Ordering::SeqCst);
+
+    manager.poll();
+    match manager.status("broken").unwrap().health {
+        Health::Degraded {
+            consecutive_failures: 1,
+        } => {}
+        other => panic!("unexpected health {:?}", other),
+    }
+
+    manager.poll();
+    match manager.status("broken").unwrap().health {
+        Health::Failed {
+            consecutive_failures: 2,
+            ..
+        } => {}
+        other => panic!("unexpected health {:?}", other),
+    }
+    assert!(manager.status("broken").unwrap().last_error.is_some());
+    assert_eq!(Health::Healthy, manager.status("office").unwrap().health);
+
+    // not retried before the retry delay elapsed
+    failing.store(false, Ordering::SeqCst);
+    manager.poll();
+    match manager.status("broken").unwrap().health {
+        Health::Failed { .. } => {}
+        other => panic!("unexpected health {:?}", other),
+    }
+}
+
+#[test]
+fn test_manager_recovers_after_retry_delay() {
+    let clock = ManualClock::new();
+    let mut manager = SensorManager::new();
+    manager.set_clock(Arc::new(clock.clone()));
+    manager.set_failure_threshold(1);
+    manager.set_retry_delay(Duration::from_secs(60));
+    let (sensor, failing) = stub_sensor();
+    manager.add("kitchen", sensor).unwrap();
+
+    failing.store(true, Ordering::SeqCst);
+    manager.poll();
+    failing.store(false, Ordering::SeqCst);
+
+    clock.advance(Duration::from_secs(59));
+    manager.poll();
+    match manager.status("kitchen").unwrap().health {
+        Health::Failed { .. } => {}
+        other => panic!("unexpected health {:?}", other),
+    }
+
+    clock.advance(Duration::from_secs(1));
+    manager.poll();
+    assert_eq!(Health::Healthy, manager.status("kitchen").unwrap().health);
+}
+
+#[test]
+fn test_manager_run_uses_clock() {
+    let clock = ManualClock::new();
+    let start = clock.now();
+    let mut manager = SensorManager::new();
+    manager.set_clock(Arc::new(clock.clone()));
+    manager.add("kitchen", stub_sensor().0).unwrap();
+
+    let mut rounds = 0;
+    manager.run(Duration::from_secs(10), |_| {
+        rounds += 1;
+        rounds < 3
+    });
+    assert_eq!(Duration::from_secs(20), clock.now() - start);
+}
+
+#[test]
+fn test_manager_remove() {
+    let mut manager = SensorManager::new();
+    manager.add("kitchen", stub_sensor().0).unwrap();
+    assert!(manager.remove("kitchen").is_some());
+    assert!(manager.remove("kitchen").is_none());
+    assert!(manager.names().is_empty());
+}
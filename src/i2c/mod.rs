@@ -22,12 +22,37 @@ OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 SOFTWARE.
  */
 
+use crate::clock::{default_clock, Clock};
+use crate::i2c::audit::{AuditAction, AuditStore};
+use crate::i2c::instrument::CommandSpan;
 use log::{debug, trace};
 use rppal::i2c::I2c;
-use std::f32::NAN;
+use std::ops::RangeInclusive;
 use std::result::Result;
-use std::time::Instant;
-use std::{error, fmt, thread, time};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use std::{error, fmt, io};
+
+pub mod analyzer;
+pub mod asc;
+pub mod audit;
+pub mod calibration;
+pub mod compensation;
+pub mod config;
+pub mod diagnostics;
+pub mod discovery;
+pub mod duty;
+mod instrument;
+pub mod manager;
+pub mod mux;
+pub mod pressure;
+pub mod record;
+pub mod sim;
+pub mod stats;
+pub mod transport;
+
+pub use self::stats::Stats;
+pub use self::transport::Transport;
 
 const CMD_START_CONTINUOUS_MEASUREMENT: u16 = 0x0010;
 const CMD_STOP_CONTINUOUS_MEASUREMENT: u16 = 0x0104;
@@ -41,11 +66,44 @@ const CMD_SET_ALTITUDE_COMPENSATION: u16 = 0x5102;
 const CMD_RESET: u16 = 0xD304;
 const CMD_GET_FIRMWARE_VERSION: u16 = 0xD100;
 
+/// Time the sensor needs to prepare the response of a read command
+const CMD_RESPONSE_DELAY: Duration = Duration::from_millis(5);
+/// Default time the readings need to stabilise after a start or configuration change, the warm-up
+/// time of the sensor
+const DEFAULT_STABILIZATION_TIME: Duration = Duration::from_secs(10);
+/// Time between two data ready requests while waiting for a new sample
+const DATA_READY_POLL_INTERVAL: Duration = Duration::from_millis(100);
+/// Time before the first retry of a failed transaction, doubled on every further retry
+const RETRY_DELAY: Duration = Duration::from_millis(10);
+/// Longest time waited before a single retry
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// Measurement range of the CO2 concentration in ppm
+const CO2_RANGE: RangeInclusive<f32> = 0.0..=40_000.0;
+/// Measure interval in seconds supported by the sensor
+pub const MEASURE_INTERVAL_RANGE: RangeInclusive<u16> = 2..=1800;
+/// Ambient pressure in mbar the sensor accepts for compensation
+pub const PRESSURE_COMPENSATION_RANGE: RangeInclusive<u16> = 700..=1400;
+/// Reference CO2 concentration in ppm the sensor accepts for a forced recalibration
+pub const FORCED_RECALIBRATION_RANGE: RangeInclusive<u16> = 400..=2000;
+/// Temperature offset in °C the sensor accepts, a word of hundredths of a degree
+pub const TEMPERATURE_OFFSET_RANGE: RangeInclusive<f32> = 0.0..=655.35;
+/// Operating range of the temperature in °C
+const TEMPERATURE_RANGE: RangeInclusive<f32> = -40.0..=70.0;
+/// Range of the relative humidity in %
+const HUMIDITY_RANGE: RangeInclusive<f32> = 0.0..=100.0;
+
+/// Default I2C slave address of the SCD30
+pub const DEFAULT_SLAVE_ADDRESS: u16 = 0x61;
+
 #[derive(Debug)]
 pub enum Error {
     I2c(rppal::i2c::Error),
     NoData(String),
     CrcError(String),
+    InvalidData(String),
+    Config(String),
+    Io(io::Error),
     NotImplemented,
 }
 
@@ -57,6 +115,9 @@ impl fmt::Display for Error {
             Error::NotImplemented => write!(f, "Operation not implemented"),
             Error::NoData(ref s) => write!(f, "NoData {}", s),
             Error::CrcError(ref s) => write!(f, "CrcError {}", s),
+            Error::InvalidData(ref s) => write!(f, "InvalidData {}", s),
+            Error::Config(ref s) => write!(f, "Config {}", s),
+            Error::Io(ref err) => write!(f, "IO error: {}", err),
         }
     }
 }
@@ -69,12 +130,52 @@ impl From<rppal::i2c::Error> for Error {
     }
 }
 
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Error {
+        Error::Io(err)
+    }
+}
+
+/// A single reading of all values measured by the sensor
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Measurement {
+    /// CO2 concentration in ppm
+    pub co2: f32,
+    /// temperature in °C
+    pub temperature: f32,
+    /// relative humidity in %
+    pub humidity: f32,
+    /// time the sensor produced the values, estimated from its measurement schedule
+    pub timestamp: Instant,
+    /// age of the values when they were handed out, counted from the timestamp
+    pub age: Duration,
+    /// true if the values were read while the sensor was still stabilising after a start, reset
+    /// or configuration change
+    pub stabilizing: bool,
+}
+
+/// Decides when cached values are good enough and when the sensor is read.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum FreshnessPolicy {
+    /// Always wait for a sample newer than the last one read, but at most `timeout`
+    WaitForNew { timeout: Duration },
+    /// Use the cached values up to the given age, otherwise wait for a new sample for at most two
+    /// measure intervals
+    MaxAge(Duration),
+    /// Read a new sample if the sensor has one, otherwise use the cached values
+    NeverBlock,
+    /// Use the cached values until the sensor is due to produce the next sample, then read a new
+    /// sample if the sensor has one
+    #[default]
+    MeasureInterval,
+}
+
 /// Structo encapsulating all the data required for the scd30 sensor
-pub struct SCD30 {
+pub struct SCD30<T: Transport = I2c> {
     /// poll intervall in seconds
     interval_in_s: u16,
-    /// i2c base handler
-    i2c: I2c,
+    /// bus the sensor is connected to, by default a plain i2c handler
+    i2c: T,
     /// last read temperature value in °C
     temperature: f32,
     /// last read humidity in %
@@ -83,58 +184,216 @@ pub struct SCD30 {
     co2: f32,
     /// timestamp of last read from the device
     last_read_time: Option<Instant>,
+    /// time the sensor produced the last read values
+    sample_time: Option<Instant>,
+    /// ambient pressure in mbar passed on the last start, 0 if not compensated
+    pressure_mbar: u16,
+    /// time source for caching and waiting
+    clock: Arc<dyn Clock>,
+    /// when cached values are used instead of reading the sensor
+    freshness: FreshnessPolicy,
+    /// start of the continuous measurement if started by this driver
+    measuring_since: Option<Instant>,
+    /// start of the current measurement schedule, the sensor produces a sample every interval
+    schedule_start: Option<Instant>,
+    /// time readings are considered stable from
+    stable_from: Option<Instant>,
+    /// time the readings need to stabilise
+    stabilization_time: Duration,
+    /// communication counters
+    stats: Stats,
+    /// number of times a failed transaction is repeated
+    retries: u8,
+    /// store calibration-affecting calls are recorded to
+    audit: Option<Box<dyn AuditStore>>,
+    /// operator note added to the audit entries
+    audit_note: Option<String>,
 }
 
 impl SCD30 {
     /// creates a new sensor with the default I2C address 0x61
     pub fn new() -> Result<SCD30, Error> {
-        SCD30::from_slave_address(0x61) // 0x61
+        SCD30::from_slave_address(DEFAULT_SLAVE_ADDRESS)
     }
 
     /// Generates the sensor from an arbitrary slave address
     /// the default address is 0x61
     pub fn from_slave_address(slave_address: u16) -> Result<SCD30, Error> {
-        let res = I2c::new();
+        SCD30::from_i2c(I2c::new(), slave_address)
+    }
+
+    /// Generates the sensor on the given I2C bus number (see `/dev/i2c-*`) and slave address
+    pub fn from_bus(bus: u8, slave_address: u16) -> Result<SCD30, Error> {
+        SCD30::from_i2c(I2c::with_bus(bus), slave_address)
+    }
+
+    fn from_i2c(res: Result<I2c, rppal::i2c::Error>, slave_address: u16) -> Result<SCD30, Error> {
         match res {
             Ok(mut an_i2c) => match an_i2c.set_slave_address(slave_address) {
                 Err(e) => Err(Error::from(e)),
-                Ok(_) => {
-                    let mut sensor = SCD30 {
-                        i2c: an_i2c,
-                        interval_in_s: 2,
-                        temperature: NAN,
-                        humidity: NAN,
-                        co2: NAN,
-                        last_read_time: None,
-                    };
-                    let _ = sensor.read_measure_interval()?;
-
-                    Ok(sensor)
-                }
+                Ok(_) => SCD30::from_transport(an_i2c),
             },
             Err(e) => Err(Error::from(e)),
         }
     }
+}
+
+impl<T: Transport> SCD30<T> {
+    /// Generates the sensor on top of an arbitrary transport, e.g. a multiplexer channel.
+    pub fn from_transport(transport: T) -> Result<SCD30<T>, Error> {
+        let mut sensor = SCD30 {
+            i2c: transport,
+            interval_in_s: 2,
+            temperature: f32::NAN,
+            humidity: f32::NAN,
+            co2: f32::NAN,
+            last_read_time: None,
+            sample_time: None,
+            pressure_mbar: 0,
+            clock: default_clock(),
+            freshness: FreshnessPolicy::default(),
+            measuring_since: None,
+            schedule_start: None,
+            stable_from: None,
+            stabilization_time: DEFAULT_STABILIZATION_TIME,
+            stats: Stats::default(),
+            retries: 0,
+            audit: None,
+            audit_note: None,
+        };
+        let _ = sensor.read_measure_interval()?;
+
+        Ok(sensor)
+    }
+
+    /// Moves the sensor to a boxed transport, so sensors on different kinds of transports can be
+    /// kept together, e.g. in a [`manager::SensorManager`].
+    pub fn boxed(self) -> SCD30<Box<dyn Transport + Send>>
+    where
+        T: Send + 'static,
+    {
+        SCD30 {
+            i2c: Box::new(self.i2c),
+            interval_in_s: self.interval_in_s,
+            temperature: self.temperature,
+            humidity: self.humidity,
+            co2: self.co2,
+            last_read_time: self.last_read_time,
+            sample_time: self.sample_time,
+            pressure_mbar: self.pressure_mbar,
+            clock: self.clock,
+            freshness: self.freshness,
+            measuring_since: self.measuring_since,
+            schedule_start: self.schedule_start,
+            stable_from: self.stable_from,
+            stabilization_time: self.stabilization_time,
+            stats: self.stats,
+            retries: self.retries,
+            audit: self.audit,
+            audit_note: self.audit_note,
+        }
+    }
+
+    /// Gets the transport back, e.g. to inspect a recording.
+    pub fn into_transport(self) -> T {
+        self.i2c
+    }
+
+    /// Replaces the time source used for caching and waiting, e.g. by a [`crate::clock::ManualClock`]
+    /// in tests.
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.clock = clock;
+    }
+
+    /// Gets the time source of the sensor
+    pub fn clock(&self) -> Arc<dyn Clock> {
+        Arc::clone(&self.clock)
+    }
+
+    /// Sets when cached values are used instead of reading the sensor. The default is
+    /// [`FreshnessPolicy::MeasureInterval`].
+    pub fn set_freshness_policy(&mut self, freshness: FreshnessPolicy) {
+        self.freshness = freshness;
+    }
+
+    /// Gets the freshness policy of the sensor
+    pub fn freshness_policy(&self) -> FreshnessPolicy {
+        self.freshness
+    }
+
+    /// Sets the time the readings need to stabilise after a start, reset, measure interval or
+    /// pressure change. Readings taken within this time are flagged as stabilizing. Defaults to 10s.
+    pub fn set_stabilization_time(&mut self, stabilization_time: Duration) {
+        self.stabilization_time = stabilization_time;
+    }
+
+    /// Gets the time the continuous measurement was started by this driver, `None` if stopped or
+    /// not started by this driver.
+    pub fn measuring_since(&self) -> Option<Instant> {
+        self.measuring_since
+    }
+
+    /// True if readings taken now are flagged as stabilizing.
+    pub fn is_stabilizing(&self) -> bool {
+        self.stable_from
+            .is_some_and(|stable_from| self.clock.now() < stable_from)
+    }
+
+    /// Flags readings as stabilizing for the stabilization time, e.g. after a detected power cycle
+    /// of the sensor.
+    pub fn mark_unstable(&mut self) {
+        self.stable_from = Some(self.clock.now() + self.stabilization_time);
+    }
+
+    /// Gets a snapshot of the communication counters
+    pub fn stats(&self) -> Stats {
+        self.stats
+    }
+
+    /// Sets all communication counters back to zero
+    pub fn reset_stats(&mut self) {
+        self.stats = Stats::default();
+    }
+
+    /// Sets how often a failed transaction is repeated before the error is returned. Every retry
+    /// waits twice as long as the one before, starting with 10ms and capped at 1s. Defaults to 0.
+    pub fn set_retries(&mut self, retries: u8) {
+        self.retries = retries;
+    }
+
+    /// Gets the number of times a failed transaction is repeated
+    pub fn retries(&self) -> u8 {
+        self.retries
+    }
 
     /// Reads the I2C bus speed
     pub fn get_bus_speed(&mut self) -> Result<u32, Error> {
-        match self.i2c.clock_speed() {
-            Ok(s) => Ok(s),
-            Err(e) => Err(Error::from(e)),
-        }
+        self.i2c.clock_speed()
     }
 
-    /// Sets the measure interval in seconds. The sensor default interval is 2s.
+    /// Sets the measure interval in seconds, 2 to 1800. The sensor default interval is 2s.
     pub fn set_measure_interval(&mut self, interval_seconds: u16) -> Result<(), Error> {
+        if !MEASURE_INTERVAL_RANGE.contains(&interval_seconds) {
+            return Err(Error::Config(format!(
+                "measure interval {} out of range {}..{}",
+                interval_seconds,
+                MEASURE_INTERVAL_RANGE.start(),
+                MEASURE_INTERVAL_RANGE.end()
+            )));
+        }
+        self.send_cmd_with_args(CMD_SET_MEASUREMENT_INTERVAL, interval_seconds)?;
         self.interval_in_s = interval_seconds;
-        let _res = self.send_cmd_with_args(CMD_SET_MEASUREMENT_INTERVAL, interval_seconds)?;
+        if self.measuring_since.is_some() {
+            self.schedule_start = Some(self.clock.now());
+            self.mark_unstable();
+        }
         Ok(())
     }
 
     /// Reads the sensor firmware version.
     pub fn read_firmware_version(&mut self) -> Result<String, Error> {
         let res = self.read_u16_with_crc(CMD_GET_FIRMWARE_VERSION)?;
-        Ok(format!("{}.{}", (res >> 8), (res & 0xff)))
+        Ok(format_firmware_version(res))
     }
 
     /// Reads the currently set measurement interval in seconds
@@ -145,107 +404,264 @@ impl SCD30 {
     }
 
     /// Reads the measurement values temperature, humidity and CO2 concentration from the sensor
+    /// according to the freshness policy. Returns the number of bytes read, 0 if the cached values
+    /// are used.
     pub fn read_measure(&mut self) -> Result<u16, Error> {
-        if self.last_read_time == None
-            || self.last_read_time.unwrap().elapsed().as_secs() > self.interval_in_s as u64
-        {
-            if self.data_available()? {
-                let mut buf = [0u8; 18];
-                let res = self.read_data(CMD_GET_MEASUREMENT, &mut buf)?;
-                if res != 18 {
-                    return Err(Error::NoData("Expected 18 bytes of data".to_string()));
+        match self.freshness {
+            FreshnessPolicy::NeverBlock => self.read_measure_if_available(),
+            FreshnessPolicy::MeasureInterval => {
+                let interval = Duration::from_secs(u64::from(self.interval_in_s));
+                match self.sample_time {
+                    Some(t) if self.clock.now() - t < interval => Ok(0),
+                    _ => self.read_measure_if_available(),
+                }
+            }
+            FreshnessPolicy::MaxAge(max_age) => match self.sample_time {
+                Some(t) if self.clock.now() - t <= max_age => Ok(0),
+                _ => {
+                    let timeout = Duration::from_secs(2 * u64::from(self.interval_in_s));
+                    self.wait_for_measure(timeout)
                 }
-                trace!("Got {} bytes of measure data: {:x?}", res, buf);
+            },
+            FreshnessPolicy::WaitForNew { timeout } => self.wait_for_measure(timeout),
+        }
+    }
 
-                self.co2 = decode_measure_value_to_u32(&buf[0..6])?;
-                self.temperature = decode_measure_value_to_u32(&buf[6..12])?;
-                self.humidity = decode_measure_value_to_u32(&buf[12..18])?;
+    /// Polls the sensor until a new sample is available and reads it.
+    fn wait_for_measure(&mut self, timeout: Duration) -> Result<u16, Error> {
+        let deadline = self.clock.now() + timeout;
+        loop {
+            let res = self.read_measure_if_available()?;
+            if res > 0 {
+                return Ok(res);
+            }
+            if self.clock.now() >= deadline {
+                return Err(Error::NoData(format!(
+                    "No new measurement within {:?}",
+                    timeout
+                )));
+            }
+            self.clock.sleep(DATA_READY_POLL_INTERVAL);
+        }
+    }
 
-                debug!(
-                    "co2 = {:.0} ppm, temp = {:.2} °C, humidity = {:.0} %",
-                    self.co2, self.temperature, self.humidity
-                );
+    /// Reads the measurement values if the sensor has a new sample.
+    fn read_measure_if_available(&mut self) -> Result<u16, Error> {
+        if self.data_available()? {
+            let mut buf = [0u8; 18];
+            let (co2, temperature, humidity) =
+                self.read_response(CMD_GET_MEASUREMENT, &mut buf, decode_measurement)?;
+            trace!("Got measure data: {:x?}", buf);
+            self.co2 = co2;
+            self.temperature = temperature;
+            self.humidity = humidity;
+
+            debug!(
+                "co2 = {:.0} ppm, temp = {:.2} °C, humidity = {:.0} %",
+                self.co2, self.temperature, self.humidity
+            );
+
+            let now = self.clock.now();
+            self.last_read_time = Some(now);
+            self.sample_time = Some(self.estimate_sample_time(now));
+            self.stats.measurements += 1;
+            return Ok(buf.len() as u16);
+        }
+        Ok(0)
+    }
 
-                self.last_read_time = Some(Instant::now());
-                return Ok(res as u16);
+    /// Estimates when the sensor produced the sample available at the given time, the last time a
+    /// sample was due according to the measurement schedule. Without a known schedule, e.g. if the
+    /// measurement was started by another program, the given time is used.
+    fn estimate_sample_time(&self, now: Instant) -> Instant {
+        let interval_s = u64::from(self.interval_in_s);
+        match self.schedule_start {
+            Some(start) if interval_s > 0 => {
+                let samples = now.saturating_duration_since(start).as_secs() / interval_s;
+                if samples == 0 {
+                    now
+                } else {
+                    start + Duration::from_secs(samples * interval_s)
+                }
             }
+            _ => now,
         }
-        Ok(0)
     }
 
-    /// Gets the temperature in degree Celsius. If the value is older than measure interval, it reads the value from
-    /// the sensor.
+    /// Gets all measured values including their age. Whether the values are read from the sensor
+    /// depends on the freshness policy. Returns `None` as long as no values could be read.
+    pub fn measurement(&mut self) -> Result<Option<Measurement>, Error> {
+        self.read_measure()?;
+        let now = self.clock.now();
+        Ok(self.sample_time.map(|timestamp| Measurement {
+            co2: self.co2,
+            temperature: self.temperature,
+            humidity: self.humidity,
+            timestamp,
+            age: now - timestamp,
+            stabilizing: self
+                .stable_from
+                .is_some_and(|stable_from| timestamp < stable_from),
+        }))
+    }
+
+    /// Gets the temperature in degree Celsius. Whether the value is read from the sensor depends on the
+    /// freshness policy.
     pub fn temperature(&mut self) -> Result<f32, Error> {
         self.read_measure()?;
         Ok(self.temperature)
     }
 
-    /// Gets the humidity in percent. If the value is older than measure interval, it reads the value from
-    /// the sensor.
+    /// Gets the humidity in percent. Whether the value is read from the sensor depends on the
+    /// freshness policy.
     pub fn humidity(&mut self) -> Result<f32, Error> {
         self.read_measure()?;
         Ok(self.humidity)
     }
 
-    /// Gets the CO2 concentration in ppm. If the value is older than measure interval, it reads the value from
-    /// the sensor.
+    /// Gets the CO2 concentration in ppm. Whether the value is read from the sensor depends on the
+    /// freshness policy.
     pub fn co2(&mut self) -> Result<f32, Error> {
         self.read_measure()?;
         Ok(self.co2)
     }
 
-    /// Enables the sensor self calibration mechanism. See also sensor documentation
+    /// Enables the sensor self calibration mechanism. See also sensor documentation and [`asc`] to
+    /// check its prerequisites.
     pub fn enable_self_calibration(&mut self) -> Result<(), Error> {
-        let _res = self.send_cmd_with_args(CMD_AUTOMATIC_SELF_CALIBRATION, 1)?;
-        Ok(())
+        self.audited(
+            AuditAction::SelfCalibration,
+            Some(true.to_string()),
+            Some(|s| Ok(s.read_self_calibration()?.to_string())),
+            |s| s.send_cmd_with_args(CMD_AUTOMATIC_SELF_CALIBRATION, 1),
+        )
     }
 
     /// Disables the sensor self calibration. See also sensor documentation.
     pub fn disable_self_calibration(&mut self) -> Result<(), Error> {
-        let _res = self.send_cmd_with_args(CMD_AUTOMATIC_SELF_CALIBRATION, 0)?;
-        Ok(())
+        self.audited(
+            AuditAction::SelfCalibration,
+            Some(false.to_string()),
+            Some(|s| Ok(s.read_self_calibration()?.to_string())),
+            |s| s.send_cmd_with_args(CMD_AUTOMATIC_SELF_CALIBRATION, 0),
+        )
     }
 
-    /// Sets the altitude compensation in meters above sea level.
+    /// Reads whether the sensor self calibration mechanism is enabled.
+    pub fn read_self_calibration(&mut self) -> Result<bool, Error> {
+        let res = self.read_u16_with_crc(CMD_AUTOMATIC_SELF_CALIBRATION)?;
+        Ok(res == 1)
+    }
+
+    /// Sets the altitude compensation in meters above sea level. It is only applied while the
+    /// measurement runs without pressure compensation, see [`compensation`].
     pub fn set_altitude_compensation(&mut self, altitude_mum: u16) -> Result<(), Error> {
-        let _res = self.send_cmd_with_args(CMD_SET_ALTITUDE_COMPENSATION, altitude_mum)?;
-        Ok(())
+        self.audited(
+            AuditAction::AltitudeCompensation,
+            Some(altitude_mum.to_string()),
+            Some(|s| Ok(s.read_altitude_compensation()?.to_string())),
+            |s| s.send_cmd_with_args(CMD_SET_ALTITUDE_COMPENSATION, altitude_mum),
+        )
+    }
+
+    /// Reads the altitude compensation in meters above sea level.
+    pub fn read_altitude_compensation(&mut self) -> Result<u16, Error> {
+        self.read_u16_with_crc(CMD_SET_ALTITUDE_COMPENSATION)
     }
 
-    /// Force sensor recalibration based on the given CO2 concentration.
+    /// Force sensor recalibration based on the given CO2 concentration. The sensor has to run at
+    /// a stable concentration for at least two minutes before, see [`calibration`] for a guided
+    /// procedure.
     pub fn set_forced_recalibration(&mut self, real_co2_ppm: u16) -> Result<(), Error> {
-        let _res = self.send_cmd_with_args(CMD_SET_FORCED_RECALIBRATION_FACTOR, real_co2_ppm)?;
-        Ok(())
+        self.audited(
+            AuditAction::ForcedRecalibration,
+            Some(real_co2_ppm.to_string()),
+            Some(|s| Ok(s.read_forced_recalibration()?.to_string())),
+            |s| s.send_cmd_with_args(CMD_SET_FORCED_RECALIBRATION_FACTOR, real_co2_ppm),
+        )
+    }
+
+    /// Reads the reference CO2 concentration in ppm of the last forced recalibration.
+    pub fn read_forced_recalibration(&mut self) -> Result<u16, Error> {
+        self.read_u16_with_crc(CMD_SET_FORCED_RECALIBRATION_FACTOR)
     }
 
-    /// Sets a temperature offset to compensate heat from a nearby device.
+    /// Sets a temperature offset to compensate heat from a nearby device. The offset must be
+    /// within 0 to 655.35 °C.
     pub fn set_temperature_offset(&mut self, temp: f32) -> Result<(), Error> {
-        let ticks = (temp * 100f32) as u16;
-        let _res = self.send_cmd_with_args(CMD_SET_TEMPERATURE_OFFSET, ticks)?;
-        Ok(())
+        if !TEMPERATURE_OFFSET_RANGE.contains(&temp) {
+            return Err(Error::Config(format!(
+                "temperature offset {} out of range {}..{}",
+                temp,
+                TEMPERATURE_OFFSET_RANGE.start(),
+                TEMPERATURE_OFFSET_RANGE.end()
+            )));
+        }
+        let ticks = (temp * 100f32).round() as u16;
+        self.audited(
+            AuditAction::TemperatureOffset,
+            Some(format!("{:.2}", temp)),
+            Some(|s| Ok(format!("{:.2}", s.read_temperature_offset()?))),
+            |s| s.send_cmd_with_args(CMD_SET_TEMPERATURE_OFFSET, ticks),
+        )
+    }
+
+    /// Reads the temperature offset in degree Celsius.
+    pub fn read_temperature_offset(&mut self) -> Result<f32, Error> {
+        let ticks = self.read_u16_with_crc(CMD_SET_TEMPERATURE_OFFSET)?;
+        Ok(ticks as f32 / 100f32)
     }
 
     /// Starts the measurement in the sensor based on the given altitude compensation in millibar.
+    /// The pressure overrides the altitude compensation, 0 falls back to it. See [`compensation`].
     pub fn start_with_alt_comp(&mut self, pressure_mbar: u16) -> Result<(), Error> {
-        let _res = self.send_cmd_with_args(CMD_START_CONTINUOUS_MEASUREMENT, pressure_mbar)?;
+        self.send_cmd_with_args(CMD_START_CONTINUOUS_MEASUREMENT, pressure_mbar)?;
+        self.pressure_mbar = pressure_mbar;
+        self.started();
         Ok(())
     }
 
     /// Starts the measurement in the sensor.
     pub fn start(&mut self) -> Result<(), Error> {
-        let _res = self.send_cmd_with_args(CMD_START_CONTINUOUS_MEASUREMENT, 0)?;
+        self.send_cmd_with_args(CMD_START_CONTINUOUS_MEASUREMENT, 0)?;
+        self.pressure_mbar = 0;
+        self.started();
         Ok(())
     }
 
+    /// Gets the ambient pressure in millibar the measurement was started with, 0 if none was given.
+    pub fn pressure_compensation(&self) -> u16 {
+        self.pressure_mbar
+    }
+
     /// Stops the sensor
     pub fn stop(&mut self) -> Result<(), Error> {
-        let _res = self.send_cmd(CMD_STOP_CONTINUOUS_MEASUREMENT)?;
+        self.send_cmd(CMD_STOP_CONTINUOUS_MEASUREMENT)?;
+        self.measuring_since = None;
+        self.schedule_start = None;
         Ok(())
     }
 
+    /// Records a (re)start of the continuous measurement.
+    fn started(&mut self) {
+        if self.measuring_since.is_none() {
+            self.measuring_since = Some(self.clock.now());
+        }
+        self.schedule_start = Some(self.clock.now());
+        self.mark_unstable();
+    }
+
     /// Soft reset the sensor
     pub fn soft_reset(&mut self) -> Result<(), Error> {
-        let _res = self.send_cmd(CMD_RESET)?;
+        self.audited(AuditAction::SoftReset, None, None, |s| {
+            s.send_cmd(CMD_RESET)
+        })?;
+        self.stats.resets += 1;
+        if self.measuring_since.is_some() {
+            self.measuring_since = Some(self.clock.now());
+            self.schedule_start = self.measuring_since;
+        }
+        self.mark_unstable();
         Ok(())
     }
 
@@ -261,43 +677,28 @@ impl SCD30 {
     /// Sends a command to the sensor. The SCS30 uses word commands. See also sensor specification.
     fn send_cmd(&mut self, command: u16) -> Result<(), Error> {
         let buf = prepare_cmd(command);
-
-        match self.i2c.write(&buf) {
-            Err(e) => Err(Error::from(e)),
-            _ => Ok(()),
-        }
+        self.write(&buf)
     }
 
     /// Sends a command to the sensor including a word argument.
     fn send_cmd_with_args(&mut self, command: u16, arguments: u16) -> Result<(), Error> {
         let buf = prepare_cmd_with_args(command, arguments);
-        match self.i2c.write(&buf) {
-            Err(e) => Err(Error::from(e)),
-            _ => Ok(()),
-        }
+        self.write(&buf)
     }
 
     #[allow(dead_code)]
     /// Reads a word from the indicate from which service/register the result comes.
     fn read_u16(&mut self, command: u16) -> Result<u16, Error> {
-        self.send_cmd(command)?;
-
-        thread::sleep(time::Duration::from_millis(5));
-
+        let buf = prepare_cmd(command);
         let mut rcv_buf = [0u8; 2];
 
-        let res = self.i2c.read(&mut rcv_buf);
-        match res {
-            Err(_) => {
-                return Err(Error::NoData("No data read".to_string()));
-            }
-            Ok(s) => {
-                if s != 2 {
-                    return Err(Error::NoData("Invalid data count read".to_string()));
-                }
-            }
+        let s = self
+            .i2c
+            .write_read(&buf, CMD_RESPONSE_DELAY, &mut rcv_buf)?;
+        if s != 2 {
+            return Err(Error::NoData("Invalid data count read".to_string()));
         }
-        let response: u16 = ((rcv_buf[0] as u16) << 8) as u16 + rcv_buf[1] as u16;
+        let response: u16 = ((rcv_buf[0] as u16) << 8) + rcv_buf[1] as u16;
         trace!("Read {} raw {:x?}", response, rcv_buf);
         Ok(response)
     }
@@ -305,52 +706,128 @@ impl SCD30 {
     /// Reads a word from the indicate from which service/register the result comes.
     /// The request is protected by CRC8.
     fn read_u16_with_crc(&mut self, command: u16) -> Result<u16, Error> {
-        self.send_cmd(command)?;
-
-        thread::sleep(time::Duration::from_millis(5));
-
         let mut rcv_buf = [0u8; 3];
-
-        let res = self.i2c.read(&mut rcv_buf);
-        match res {
-            Err(_) => {
-                return Err(Error::NoData("No data read".to_string()));
-            }
-            Ok(s) => {
-                if s != 3 {
-                    return Err(Error::NoData("Invalid data count read".to_string()));
-                }
-            }
-        }
-        if calculate_crc8(&rcv_buf) != 0 {
-            return Err(Error::CrcError("Invalid in result word".to_string()));
-        }
-        let response: u16 = ((rcv_buf[0] as u16) << 8) as u16 + rcv_buf[1] as u16;
+        let response = self.read_response(command, &mut rcv_buf, decode_word)?;
         trace!("Read {} raw {:#x?}", response, rcv_buf);
         Ok(response)
     }
 
-    /// Reads data from a sensor service/register to out buffer.
-    fn read_data(&mut self, command: u16, out_buf: &mut [u8]) -> Result<usize, Error> {
-        self.send_cmd(command)?;
-
-        thread::sleep(time::Duration::from_millis(5));
+    /// Writes a command buffer to the sensor, repeated on failure.
+    fn write(&mut self, buf: &[u8]) -> Result<(), Error> {
+        let span = CommandSpan::enter(buf, self.clock.now());
+        let mut attempt = 0;
+        let res = loop {
+            self.stats.commands += 1;
+            match self.i2c.write(buf) {
+                Ok(()) => {
+                    self.stats.last_success = Some(self.clock.now());
+                    break Ok(());
+                }
+                Err(e) => {
+                    self.stats.i2c_errors += 1;
+                    if let Err(e) = self.retry_or_fail(&mut attempt, e) {
+                        break Err(e);
+                    }
+                }
+            }
+        };
+        span.finish(&res, buf.len(), u16::from(attempt) + 1, self.clock.now());
+        res
+    }
 
-        let res = self.i2c.read(out_buf);
+    /// Reads the response of a sensor service/register to the out buffer and decodes it. The
+    /// transaction is repeated on failure.
+    fn read_response<R>(
+        &mut self,
+        command: u16,
+        out_buf: &mut [u8],
+        decode: impl Fn(&[u8]) -> Result<R, Error>,
+    ) -> Result<R, Error> {
+        let buf = prepare_cmd(command);
+        let span = CommandSpan::enter(&buf, self.clock.now());
+        let mut attempt = 0;
+        let mut read;
+        let res = loop {
+            self.stats.commands += 1;
+            let res = match self.i2c.write_read(&buf, CMD_RESPONSE_DELAY, out_buf) {
+                Ok(count) if count < out_buf.len() => {
+                    read = count;
+                    self.stats.short_reads += 1;
+                    Err(Error::NoData(format!(
+                        "Read {} of {} bytes",
+                        count,
+                        out_buf.len()
+                    )))
+                }
+                Ok(count) => {
+                    read = count;
+                    let res = decode(&out_buf[..count]);
+                    if let Err(Error::CrcError(_)) = res {
+                        self.stats.crc_failures += 1;
+                    }
+                    res
+                }
+                Err(e) => {
+                    read = 0;
+                    self.stats.i2c_errors += 1;
+                    Err(e)
+                }
+            };
+            match res {
+                Ok(response) => {
+                    self.stats.last_success = Some(self.clock.now());
+                    break Ok(response);
+                }
+                Err(e) => {
+                    if let Err(e) = self.retry_or_fail(&mut attempt, e) {
+                        break Err(e);
+                    }
+                }
+            }
+        };
+        span.finish(&res, read, u16::from(attempt) + 1, self.clock.now());
+        res
+    }
 
-        match res {
-            Err(_) => Err(Error::NoData("No data read".to_string())),
-            Ok(s) => Ok(s),
+    /// Records a failed transaction and waits before the next attempt. Returns the error if no
+    /// retries are left.
+    fn retry_or_fail(&mut self, attempt: &mut u8, error: Error) -> Result<(), Error> {
+        self.stats.last_error = Some(self.clock.now());
+        if *attempt >= self.retries {
+            return Err(error);
         }
+        debug!("Retrying after {}", error);
+        let delay = RETRY_DELAY * 2u32.saturating_pow(u32::from(*attempt));
+        self.clock.sleep(delay.min(MAX_RETRY_DELAY));
+        *attempt += 1;
+        self.stats.retries += 1;
+        Ok(())
+    }
+}
+
+/// Gets the name of a sensor command as used in the [SCD30 Reference]
+///
+/// [SCD30 Reference]: https://www.sensirion.com/fileadmin/user_upload/customers/sensirion/Dokumente/9.5_CO2/Sensirion_CO2_Sensors_SCD30_Interface_Description.pdf
+pub fn command_name(command: u16) -> Option<&'static str> {
+    match command {
+        CMD_START_CONTINUOUS_MEASUREMENT => Some("start continuous measurement"),
+        CMD_STOP_CONTINUOUS_MEASUREMENT => Some("stop continuous measurement"),
+        CMD_SET_MEASUREMENT_INTERVAL => Some("measurement interval"),
+        CMD_GET_DATA_READY => Some("get data ready status"),
+        CMD_GET_MEASUREMENT => Some("read measurement"),
+        CMD_AUTOMATIC_SELF_CALIBRATION => Some("automatic self calibration"),
+        CMD_SET_FORCED_RECALIBRATION_FACTOR => Some("forced recalibration value"),
+        CMD_SET_TEMPERATURE_OFFSET => Some("temperature offset"),
+        CMD_SET_ALTITUDE_COMPENSATION => Some("altitude compensation"),
+        CMD_RESET => Some("soft reset"),
+        CMD_GET_FIRMWARE_VERSION => Some("read firmware version"),
+        _ => None,
     }
 }
 
 /// Prepares a command buffer
 pub fn prepare_cmd(command: u16) -> Vec<u8> {
-    let mut res_buf = Vec::<u8>::with_capacity(2);
-    res_buf.push((command >> 8) as u8);
-    res_buf.push((command & 0xff) as u8);
-    res_buf
+    vec![(command >> 8) as u8, (command & 0xff) as u8]
 }
 
 /// Prepares a command buffer including an argument word
@@ -359,7 +836,6 @@ pub fn prepare_cmd_with_args(command: u16, arguments: u16) -> Vec<u8> {
     prepare_cmd_with_buf(command, &arg_buffer, true)
 }
 
-
 /// Prepare a command with a whole byte buffer. You can indicate whether you
 /// want to create a crc or not
 pub fn prepare_cmd_with_buf(command: u16, buf: &[u8], with_crc: bool) -> Vec<u8> {
@@ -368,15 +844,34 @@ pub fn prepare_cmd_with_buf(command: u16, buf: &[u8], with_crc: bool) -> Vec<u8>
     res_buf.push((command & 0xff) as u8);
     res_buf.extend_from_slice(buf);
 
-    if with_crc && buf.len() > 0 {
+    if with_crc && !buf.is_empty() {
         res_buf.push(calculate_crc8(buf));
     }
     trace!("Buf for cmd 0x{:0x} : {:0x?}", command, res_buf);
     res_buf
 }
 
-/// decodes the measurement value from the received 8 bytes
+/// decodes a CRC protected word from the received 3 bytes
+pub fn decode_word(data: &[u8]) -> Result<u16, Error> {
+    if data.len() != 3 {
+        return Err(Error::NoData("Invalid data count read".to_string()));
+    }
+    if calculate_crc8(data) != 0 {
+        return Err(Error::CrcError("Invalid in result word".to_string()));
+    }
+    Ok(((data[0] as u16) << 8) + data[1] as u16)
+}
+
+/// Formats the firmware version word as "major.minor"
+pub(crate) fn format_firmware_version(version: u16) -> String {
+    format!("{}.{}", (version >> 8), (version & 0xff))
+}
+
+/// decodes the measurement value from the received 6 bytes. NaN and infinite values are rejected.
 pub fn decode_measure_value_to_u32(data: &[u8]) -> Result<f32, Error> {
+    if data.len() != 6 {
+        return Err(Error::NoData("Expected 6 bytes of data".to_string()));
+    }
     if calculate_crc8(&data[0..3]) == 0 && calculate_crc8(&data[3..6]) == 0 {
         let mut val: u32 = data[0] as u32;
         val <<= 8;
@@ -385,12 +880,43 @@ pub fn decode_measure_value_to_u32(data: &[u8]) -> Result<f32, Error> {
         val |= data[3] as u32;
         val <<= 8;
         val |= data[4] as u32;
-        Ok(f32::from_bits(val))
+        let value = f32::from_bits(val);
+        if value.is_finite() {
+            Ok(value)
+        } else {
+            Err(Error::InvalidData(format!("Not a finite value {:#x}", val)))
+        }
     } else {
         Err(Error::CrcError("Invalid CRC".to_string()))
     }
 }
 
+/// decodes CO2 in ppm, temperature in °C and humidity in % from the received 18 bytes. Values
+/// outside of what the sensor can measure are rejected.
+pub fn decode_measurement(data: &[u8]) -> Result<(f32, f32, f32), Error> {
+    if data.len() != 18 {
+        return Err(Error::NoData("Expected 18 bytes of data".to_string()));
+    }
+    let co2 = decode_measure_value_to_u32(&data[0..6])?;
+    let temperature = decode_measure_value_to_u32(&data[6..12])?;
+    let humidity = decode_measure_value_to_u32(&data[12..18])?;
+    check_range("co2", co2, CO2_RANGE)?;
+    check_range("temperature", temperature, TEMPERATURE_RANGE)?;
+    check_range("humidity", humidity, HUMIDITY_RANGE)?;
+    Ok((co2, temperature, humidity))
+}
+
+fn check_range(name: &str, value: f32, range: RangeInclusive<f32>) -> Result<(), Error> {
+    if range.contains(&value) {
+        Ok(())
+    } else {
+        Err(Error::InvalidData(format!(
+            "{} = {} outside of {:?}",
+            name, value, range
+        )))
+    }
+}
+
 /// Calculates a CRC-8 with following attributes:
 ///   - Polynomial: 0x31 (x⁸ + x⁵ + x⁴ + x⁰)
 ///   - Initialization: 0xFF
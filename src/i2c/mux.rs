@@ -0,0 +1,181 @@
+/*
+MIT License
+
+Copyright (c) 2021 Crispin Tschirky <ct@fhr.ch>
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+ */
+
+//! Support for the TCA9548A I2C multiplexer.
+//!
+//! All SCD30 sensors share the fixed address 0x61, so a bus can only hold a single sensor. Placing
+//! the sensors behind a TCA9548A allows up to eight of them per multiplexer. Every [`MuxChannel`]
+//! selects its channel before each transaction and keeps the bus locked until the transaction is
+//! complete, so several sensors can be used from different threads of the same process.
+//!
+//! ```no_run
+//! use scd30pi::i2c::mux::{Tca9548a, DEFAULT_MUX_ADDRESS};
+//!
+//! let mux = Tca9548a::new(1, DEFAULT_MUX_ADDRESS).unwrap();
+//! let mut kitchen = mux.sensor(0).unwrap();
+//! let mut office = mux.sensor(1).unwrap();
+//!
+//! kitchen.start().unwrap();
+//! office.start().unwrap();
+//! ```
+
+use crate::i2c::{Error, Transport, DEFAULT_SLAVE_ADDRESS, SCD30};
+use log::trace;
+use rppal::i2c::I2c;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+/// Default I2C address of the TCA9548A with A0..A2 pulled low
+pub const DEFAULT_MUX_ADDRESS: u16 = 0x70;
+/// Number of channels of the TCA9548A
+pub const MUX_CHANNELS: u8 = 8;
+
+/// Bus shared by all channels of a multiplexer.
+struct MuxBus<T> {
+    /// bus the multiplexer is connected to
+    transport: T,
+    /// address of the multiplexer
+    mux_address: u16,
+}
+
+impl<T: Transport> MuxBus<T> {
+    /// Routes the bus to the given channel and addresses the given slave on it.
+    fn select(&mut self, channel: u8, slave_address: u16) -> Result<(), Error> {
+        self.transport.set_slave_address(self.mux_address)?;
+        self.transport.write(&[channel_mask(channel)?])?;
+        self.transport.set_slave_address(slave_address)?;
+        trace!(
+            "Selected mux channel {} slave {:#x}",
+            channel,
+            slave_address
+        );
+        Ok(())
+    }
+}
+
+/// A TCA9548A multiplexer on an I2C bus.
+pub struct Tca9548a<T = I2c> {
+    bus: Arc<Mutex<MuxBus<T>>>,
+}
+
+impl Tca9548a {
+    /// Opens the multiplexer on the given I2C bus number and address
+    pub fn new(bus: u8, mux_address: u16) -> Result<Tca9548a, Error> {
+        Ok(Tca9548a::from_i2c(I2c::with_bus(bus)?, mux_address))
+    }
+
+    /// Uses an already opened I2C bus for the multiplexer at the given address
+    pub fn from_i2c(i2c: I2c, mux_address: u16) -> Tca9548a {
+        Tca9548a::from_transport(i2c, mux_address)
+    }
+}
+
+impl<T: Transport> Tca9548a<T> {
+    /// Uses the given bus for the multiplexer at the given address. The bus must support
+    /// [`Transport::set_slave_address`].
+    pub fn from_transport(transport: T, mux_address: u16) -> Tca9548a<T> {
+        Tca9548a {
+            bus: Arc::new(Mutex::new(MuxBus {
+                transport,
+                mux_address,
+            })),
+        }
+    }
+
+    /// Creates a transport to the slave with the given address behind the given channel (0..7).
+    pub fn channel(&self, channel: u8, slave_address: u16) -> Result<MuxChannel<T>, Error> {
+        channel_mask(channel)?;
+        Ok(MuxChannel {
+            bus: Arc::clone(&self.bus),
+            channel,
+            slave_address,
+        })
+    }
+
+    /// Creates a sensor with the default address behind the given channel (0..7).
+    pub fn sensor(&self, channel: u8) -> Result<SCD30<MuxChannel<T>>, Error> {
+        SCD30::from_transport(self.channel(channel, DEFAULT_SLAVE_ADDRESS)?)
+    }
+}
+
+/// Transport to a single slave behind a channel of a [`Tca9548a`].
+pub struct MuxChannel<T = I2c> {
+    bus: Arc<Mutex<MuxBus<T>>>,
+    channel: u8,
+    slave_address: u16,
+}
+
+impl<T: Transport> MuxChannel<T> {
+    /// Gets the multiplexer channel of the transport
+    pub fn channel(&self) -> u8 {
+        self.channel
+    }
+
+    /// Locks the bus and selects the channel. The bus stays locked until the guard is dropped.
+    fn lock(&self) -> Result<MutexGuard<'_, MuxBus<T>>, Error> {
+        // a panic while holding the lock leaves no inconsistent state as the
+        // channel is selected again for every transaction
+        let mut bus = self.bus.lock().unwrap_or_else(|e| e.into_inner());
+        bus.select(self.channel, self.slave_address)?;
+        Ok(bus)
+    }
+}
+
+impl<T: Transport> Transport for MuxChannel<T> {
+    fn write(&mut self, buf: &[u8]) -> Result<(), Error> {
+        let mut bus = self.lock()?;
+        bus.transport.write(buf)
+    }
+
+    fn write_read(
+        &mut self,
+        buf: &[u8],
+        delay: Duration,
+        out_buf: &mut [u8],
+    ) -> Result<usize, Error> {
+        let mut bus = self.lock()?;
+        bus.transport.write_read(buf, delay, out_buf)
+    }
+
+    fn clock_speed(&self) -> Result<u32, Error> {
+        let bus = self.bus.lock().unwrap_or_else(|e| e.into_inner());
+        bus.transport.clock_speed()
+    }
+}
+
+/// Gets the control register value enabling only the given channel.
+pub(crate) fn channel_mask(channel: u8) -> Result<u8, Error> {
+    if channel < MUX_CHANNELS {
+        Ok(1 << channel)
+    } else {
+        Err(Error::Config(format!(
+            "mux channel {} out of range 0..{}",
+            channel,
+            MUX_CHANNELS - 1
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests;
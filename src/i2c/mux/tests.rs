@@ -0,0 +1,194 @@
+/*
+MIT License
+
+Copyright (c) 2021 Crispin Tschirky <ct@fhr.ch>
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+ */
+
+use crate::i2c::mux::{channel_mask, Tca9548a, DEFAULT_MUX_ADDRESS};
+use crate::i2c::{calculate_crc8, Error, Transport, DEFAULT_SLAVE_ADDRESS};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/// What happened on the bus behind the multiplexer
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Event {
+    /// the channel was selected
+    Select(u8),
+    /// a transaction with the sensor behind the channel
+    Transaction(u8),
+}
+
+/// Sensor answering the firmware version and measure interval commands
+struct FakeSensor {
+    firmware_version: u16,
+    measure_interval: u16,
+}
+
+impl FakeSensor {
+    fn new(firmware_version: u16) -> FakeSensor {
+        FakeSensor {
+            firmware_version,
+            measure_interval: 2,
+        }
+    }
+
+    fn write(&mut self, buf: &[u8]) -> Result<(), Error> {
+        match buf {
+            [0x46, 0x00, msb, lsb, _crc] => {
+                self.measure_interval = u16::from_be_bytes([*msb, *lsb]);
+                Ok(())
+            }
+            _ => Err(Error::NotImplemented),
+        }
+    }
+
+    fn write_read(&mut self, buf: &[u8], out_buf: &mut [u8]) -> Result<usize, Error> {
+        let word = match buf {
+            [0xd1, 0x00] => self.firmware_version,
+            [0x46, 0x00] => self.measure_interval,
+            _ => return Err(Error::NotImplemented),
+        };
+        let bytes = word.to_be_bytes();
+        out_buf[..3].copy_from_slice(&[bytes[0], bytes[1], calculate_crc8(&bytes)]);
+        Ok(3)
+    }
+}
+
+struct BusState {
+    slave_address: u16,
+    control: u8,
+    sensors: Vec<FakeSensor>,
+    events: Vec<Event>,
+}
+
+impl BusState {
+    /// Gets the sensor behind the only enabled channel
+    fn sensor(&mut self) -> Result<&mut FakeSensor, Error> {
+        if self.slave_address != DEFAULT_SLAVE_ADDRESS || self.control.count_ones() != 1 {
+            return Err(Error::NoData("no sensor addressed".to_string()));
+        }
+        let channel = self.control.trailing_zeros() as u8;
+        self.events.push(Event::Transaction(channel));
+        self.sensors
+            .get_mut(usize::from(channel))
+            .ok_or_else(|| Error::NoData("no sensor on channel".to_string()))
+    }
+}
+
+/// Bus with a TCA9548A and a sensor on each of the first channels
+#[derive(Clone)]
+struct FakeMuxBus {
+    state: Arc<Mutex<BusState>>,
+}
+
+impl FakeMuxBus {
+    fn new(sensors: Vec<FakeSensor>) -> FakeMuxBus {
+        FakeMuxBus {
+            state: Arc::new(Mutex::new(BusState {
+                slave_address: 0,
+                control: 0,
+                sensors,
+                events: Vec::new(),
+            })),
+        }
+    }
+
+    fn events(&self) -> Vec<Event> {
+        self.state.lock().unwrap().events.clone()
+    }
+
+    fn measure_interval(&self, channel: usize) -> u16 {
+        self.state.lock().unwrap().sensors[channel].measure_interval
+    }
+}
+
+impl Transport for FakeMuxBus {
+    fn write(&mut self, buf: &[u8]) -> Result<(), Error> {
+        let mut state = self.state.lock().unwrap();
+        if state.slave_address == DEFAULT_MUX_ADDRESS {
+            state.control = buf[0];
+            let channel = buf[0].trailing_zeros() as u8;
+            state.events.push(Event::Select(channel));
+            return Ok(());
+        }
+        state.sensor()?.write(buf)
+    }
+
+    fn write_read(
+        &mut self,
+        buf: &[u8],
+        _delay: Duration,
+        out_buf: &mut [u8],
+    ) -> Result<usize, Error> {
+        let mut state = self.state.lock().unwrap();
+        state.sensor()?.write_read(buf, out_buf)
+    }
+
+    fn set_slave_address(&mut self, slave_address: u16) -> Result<(), Error> {
+        self.state.lock().unwrap().slave_address = slave_address;
+        Ok(())
+    }
+}
+
+#[test]
+fn test_channel_mask() {
+    assert_eq!(0x01, channel_mask(0).unwrap());
+    assert_eq!(0x08, channel_mask(3).unwrap());
+    assert_eq!(0x80, channel_mask(7).unwrap());
+}
+
+#[test]
+fn test_channel_mask_out_of_range() {
+    assert!(channel_mask(8).is_err());
+}
+
+#[test]
+fn test_channels_select_before_every_transaction() {
+    let bus = FakeMuxBus::new(vec![FakeSensor::new(0x0342), FakeSensor::new(0x0242)]);
+    let mux = Tca9548a::from_transport(bus.clone(), DEFAULT_MUX_ADDRESS);
+    let mut kitchen = mux.sensor(0).unwrap();
+    let mut office = mux.sensor(1).unwrap();
+    assert!(mux.sensor(8).is_err());
+
+    let kitchen_thread = thread::spawn(move || {
+        for _ in 0..50 {
+            assert_eq!("3.66", kitchen.read_firmware_version().unwrap());
+        }
+        kitchen.set_measure_interval(10).unwrap();
+    });
+    for _ in 0..50 {
+        assert_eq!("2.66", office.read_firmware_version().unwrap());
+    }
+    office.set_measure_interval(20).unwrap();
+    kitchen_thread.join().unwrap();
+
+    assert_eq!(10, bus.measure_interval(0));
+    assert_eq!(20, bus.measure_interval(1));
+    let events = bus.events();
+    // creation, 50 reads and the interval per sensor, each with its select
+    assert_eq!(2 * 2 * 52, events.len());
+    for (i, event) in events.iter().enumerate() {
+        if let Event::Transaction(channel) = event {
+            assert_eq!(Event::Select(*channel), events[i - 1], "event {}", i);
+        }
+    }
+}
@@ -0,0 +1,159 @@
+/*
+MIT License
+
+Copyright (c) 2021 Crispin Tschirky <ct@fhr.ch>
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+ */
+
+//! Live pressure compensation from an external barometer.
+//!
+//! [`SCD30::start_with_alt_comp`] takes the ambient pressure only once, but the pressure drifts
+//! with the weather and the CO2 reading with it. A [`PressureCompensator`] reads a [`Barometer`]
+//! and re-issues the pressure to the running sensor once it changed beyond a threshold. Updates are
+//! rate limited, as every restart of the measurement flags the readings as stabilizing.
+//!
+//! ```no_run
+//! use scd30pi::i2c::pressure::{Bmp280, PressureCompensator};
+//! use scd30pi::i2c::SCD30;
+//! use std::{thread, time::Duration};
+//!
+//! let mut sensor = SCD30::new().unwrap();
+//! let mut compensator = PressureCompensator::new(Bmp280::new().unwrap());
+//! sensor.start().unwrap();
+//! loop {
+//!     if let Some(pressure) = compensator.update(&mut sensor).unwrap() {
+//!         println!("compensating {} mbar", pressure);
+//!     }
+//!     thread::sleep(Duration::from_secs(60));
+//! }
+//! ```
+
+mod bmp280;
+
+pub use self::bmp280::{Bmp280, BMP280_ALTERNATE_ADDRESS, BMP280_DEFAULT_ADDRESS};
+
+pub use crate::i2c::PRESSURE_COMPENSATION_RANGE;
+
+use crate::i2c::compensation::to_mbar;
+use crate::i2c::{Error, Transport, SCD30};
+use log::debug;
+use std::time::{Duration, Instant};
+
+/// A sensor measuring the ambient pressure.
+pub trait Barometer {
+    /// Reads the ambient pressure in hPa (= mbar).
+    fn pressure(&mut self) -> Result<f32, Error>;
+}
+
+impl<B: Barometer + ?Sized> Barometer for Box<B> {
+    fn pressure(&mut self) -> Result<f32, Error> {
+        (**self).pressure()
+    }
+}
+
+/// Keeps the pressure compensation of a running sensor up to date.
+pub struct PressureCompensator<B: Barometer> {
+    /// source of the ambient pressure
+    barometer: B,
+    /// change in mbar which triggers an update
+    threshold: f32,
+    /// minimum time between two updates
+    min_interval: Duration,
+    /// last pressure read from the barometer in mbar
+    last_pressure: Option<f32>,
+    /// time of the last update of the sensor
+    last_update: Option<Instant>,
+}
+
+impl<B: Barometer> PressureCompensator<B> {
+    /// Creates a compensator updating on changes of 2 mbar at most every 5 minutes.
+    pub fn new(barometer: B) -> PressureCompensator<B> {
+        PressureCompensator {
+            barometer,
+            threshold: 2.0,
+            min_interval: Duration::from_secs(300),
+            last_pressure: None,
+            last_update: None,
+        }
+    }
+
+    /// Sets the change in mbar from the compensated pressure which triggers an update
+    pub fn set_threshold(&mut self, threshold: f32) {
+        self.threshold = threshold;
+    }
+
+    /// Gets the change in mbar which triggers an update
+    pub fn threshold(&self) -> f32 {
+        self.threshold
+    }
+
+    /// Sets the minimum time between two updates of the sensor
+    pub fn set_min_interval(&mut self, min_interval: Duration) {
+        self.min_interval = min_interval;
+    }
+
+    /// Gets the minimum time between two updates of the sensor
+    pub fn min_interval(&self) -> Duration {
+        self.min_interval
+    }
+
+    /// Gets the last pressure in mbar read from the barometer
+    pub fn last_pressure(&self) -> Option<f32> {
+        self.last_pressure
+    }
+
+    /// Gets the barometer, e.g. to read its temperature
+    pub fn barometer_mut(&mut self) -> &mut B {
+        &mut self.barometer
+    }
+
+    /// Reads the barometer and re-issues the pressure to the sensor if it changed beyond the
+    /// threshold and the last update is older than the minimum interval. A sensor without pressure
+    /// compensation is updated right away. Nothing is done if the measurement was not started by
+    /// this driver. Returns the new pressure in mbar if the sensor was updated.
+    pub fn update<T: Transport>(&mut self, sensor: &mut SCD30<T>) -> Result<Option<u16>, Error> {
+        if sensor.measuring_since().is_none() {
+            return Ok(None);
+        }
+        let pressure = self.barometer.pressure()?;
+        self.last_pressure = Some(pressure);
+        let mbar = to_mbar(pressure)?;
+
+        let current = sensor.pressure_compensation();
+        if current != 0 && (pressure - f32::from(current)).abs() < self.threshold {
+            return Ok(None);
+        }
+        let now = sensor.clock().now();
+        if let Some(last_update) = self.last_update {
+            if now - last_update < self.min_interval {
+                debug!("Pressure update to {} mbar delayed by rate limit", pressure);
+                return Ok(None);
+            }
+        }
+
+        sensor.start_with_alt_comp(mbar)?;
+        self.last_update = Some(now);
+        debug!("Pressure compensation updated to {} mbar", mbar);
+        Ok(Some(mbar))
+    }
+}
+
+#[cfg(test)]
+mod tests;
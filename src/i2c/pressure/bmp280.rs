@@ -0,0 +1,213 @@
+/*
+MIT License
+
+Copyright (c) 2021 Crispin Tschirky <ct@fhr.ch>
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+ */
+
+//! Driver for the Bosch BMP280 and BME280 barometric pressure sensors.
+
+use crate::i2c::pressure::Barometer;
+use crate::i2c::{Error, Transport};
+use rppal::i2c::I2c;
+use std::time::Duration;
+
+/// I2C address of the BMP280 with SDO pulled low
+pub const BMP280_DEFAULT_ADDRESS: u16 = 0x76;
+/// I2C address of the BMP280 with SDO pulled high
+pub const BMP280_ALTERNATE_ADDRESS: u16 = 0x77;
+
+const REG_CALIBRATION: u8 = 0x88;
+const REG_CHIP_ID: u8 = 0xD0;
+const REG_CTRL_MEAS: u8 = 0xF4;
+const REG_DATA: u8 = 0xF7;
+
+/// Chip id of the BMP280
+const CHIP_ID_BMP280: u8 = 0x58;
+/// Chip id of the BME280
+const CHIP_ID_BME280: u8 = 0x60;
+
+/// Temperature oversampling x1, pressure oversampling x4, normal mode
+const CTRL_MEAS_NORMAL: u8 = 0x2F;
+
+/// Trimming parameters stored in the sensor
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Calibration {
+    t1: u16,
+    t2: i16,
+    t3: i16,
+    p1: u16,
+    p2: i16,
+    p3: i16,
+    p4: i16,
+    p5: i16,
+    p6: i16,
+    p7: i16,
+    p8: i16,
+    p9: i16,
+}
+
+impl Calibration {
+    /// Decodes the 24 bytes of trimming parameters starting at 0x88
+    pub(crate) fn decode(data: &[u8; 24]) -> Calibration {
+        let u = |i: usize| u16::from_le_bytes([data[i], data[i + 1]]);
+        let s = |i: usize| i16::from_le_bytes([data[i], data[i + 1]]);
+        Calibration {
+            t1: u(0),
+            t2: s(2),
+            t3: s(4),
+            p1: u(6),
+            p2: s(8),
+            p3: s(10),
+            p4: s(12),
+            p5: s(14),
+            p6: s(16),
+            p7: s(18),
+            p8: s(20),
+            p9: s(22),
+        }
+    }
+
+    /// Compensates the raw readings to the temperature in °C and the pressure in Pa, with the
+    /// floating point formulas of the datasheet.
+    pub(crate) fn compensate(&self, adc_t: i32, adc_p: i32) -> (f64, f64) {
+        let adc_t = f64::from(adc_t);
+        let t1 = f64::from(self.t1);
+        let var1 = (adc_t / 16384.0 - t1 / 1024.0) * f64::from(self.t2);
+        let var2 = (adc_t / 131_072.0 - t1 / 8192.0).powi(2) * f64::from(self.t3);
+        let t_fine = var1 + var2;
+        let temperature = t_fine / 5120.0;
+
+        let mut var1 = t_fine / 2.0 - 64000.0;
+        let mut var2 = var1 * var1 * f64::from(self.p6) / 32768.0;
+        var2 += var1 * f64::from(self.p5) * 2.0;
+        var2 = var2 / 4.0 + f64::from(self.p4) * 65536.0;
+        var1 =
+            (f64::from(self.p3) * var1 * var1 / 524_288.0 + f64::from(self.p2) * var1) / 524_288.0;
+        var1 = (1.0 + var1 / 32768.0) * f64::from(self.p1);
+        if var1 == 0.0 {
+            return (temperature, 0.0);
+        }
+        let mut p = 1_048_576.0 - f64::from(adc_p);
+        p = (p - var2 / 4096.0) * 6250.0 / var1;
+        let var1 = f64::from(self.p9) * p * p / 2_147_483_648.0;
+        let var2 = p * f64::from(self.p8) / 32768.0;
+        let pressure = p + (var1 + var2 + f64::from(self.p7)) / 16.0;
+        (temperature, pressure)
+    }
+}
+
+/// A BMP280 or BME280 running in normal mode.
+pub struct Bmp280<T: Transport = I2c> {
+    /// bus the sensor is connected to
+    i2c: T,
+    /// trimming parameters read from the sensor
+    calibration: Calibration,
+    /// chip id read from the sensor
+    chip_id: u8,
+}
+
+impl Bmp280 {
+    /// Creates the sensor with the default I2C address 0x76
+    pub fn new() -> Result<Bmp280, Error> {
+        let mut i2c = I2c::new()?;
+        i2c.set_slave_address(BMP280_DEFAULT_ADDRESS)?;
+        Bmp280::from_transport(i2c)
+    }
+
+    /// Creates the sensor on the given I2C bus number (see `/dev/i2c-*`) and slave address
+    pub fn from_bus(bus: u8, slave_address: u16) -> Result<Bmp280, Error> {
+        let mut i2c = I2c::with_bus(bus)?;
+        i2c.set_slave_address(slave_address)?;
+        Bmp280::from_transport(i2c)
+    }
+}
+
+impl<T: Transport> Bmp280<T> {
+    /// Creates the sensor on top of an arbitrary transport. Checks the chip id, reads the trimming
+    /// parameters and starts the measurement in normal mode.
+    pub fn from_transport(transport: T) -> Result<Bmp280<T>, Error> {
+        let mut i2c = transport;
+        let mut chip_id = [0u8];
+        read_register(&mut i2c, REG_CHIP_ID, &mut chip_id)?;
+        if chip_id[0] != CHIP_ID_BMP280 && chip_id[0] != CHIP_ID_BME280 {
+            return Err(Error::InvalidData(format!(
+                "unknown barometer chip id {:#x}",
+                chip_id[0]
+            )));
+        }
+        let mut data = [0u8; 24];
+        read_register(&mut i2c, REG_CALIBRATION, &mut data)?;
+        i2c.write(&[REG_CTRL_MEAS, CTRL_MEAS_NORMAL])?;
+        Ok(Bmp280 {
+            i2c,
+            calibration: Calibration::decode(&data),
+            chip_id: chip_id[0],
+        })
+    }
+
+    /// True if the sensor is a BME280
+    pub fn is_bme280(&self) -> bool {
+        self.chip_id == CHIP_ID_BME280
+    }
+
+    /// Reads the temperature in °C and the pressure in hPa
+    pub fn read(&mut self) -> Result<(f32, f32), Error> {
+        let mut data = [0u8; 6];
+        read_register(&mut self.i2c, REG_DATA, &mut data)?;
+        let adc_p =
+            (i32::from(data[0]) << 12) | (i32::from(data[1]) << 4) | (i32::from(data[2]) >> 4);
+        let adc_t =
+            (i32::from(data[3]) << 12) | (i32::from(data[4]) << 4) | (i32::from(data[5]) >> 4);
+        let (temperature, pressure) = self.calibration.compensate(adc_t, adc_p);
+        if pressure <= 0.0 {
+            return Err(Error::InvalidData("barometer not calibrated".to_string()));
+        }
+        Ok((temperature as f32, (pressure / 100.0) as f32))
+    }
+
+    /// Gets the transport back
+    pub fn into_transport(self) -> T {
+        self.i2c
+    }
+}
+
+impl<T: Transport> Barometer for Bmp280<T> {
+    fn pressure(&mut self) -> Result<f32, Error> {
+        Ok(self.read()?.1)
+    }
+}
+
+/// Reads consecutive registers starting at the given one
+fn read_register<T: Transport>(i2c: &mut T, register: u8, out_buf: &mut [u8]) -> Result<(), Error> {
+    let count = i2c.write_read(&[register], Duration::from_millis(0), out_buf)?;
+    if count != out_buf.len() {
+        return Err(Error::NoData(format!(
+            "Read {} of {} bytes from register {:#x}",
+            count,
+            out_buf.len(),
+            register
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,104 @@
+/*
+MIT License
+
+Copyright (c) 2021 Crispin Tschirky <ct@fhr.ch>
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+ */
+
+use crate::i2c::pressure::{Barometer, Bmp280};
+use crate::i2c::{Error, Transport};
+use std::time::Duration;
+
+/// Register file of a BMP280 with the trimming parameters and readings of the datasheet example
+struct FakeBmp280 {
+    registers: [u8; 256],
+}
+
+impl FakeBmp280 {
+    fn new(chip_id: u8) -> FakeBmp280 {
+        let mut registers = [0u8; 256];
+        registers[0xD0] = chip_id;
+        let calibration: [i32; 12] = [
+            27504, 26435, -1000, 36477, -10685, 3024, 2855, 140, -7, 15500, -14600, 6000,
+        ];
+        for (i, value) in calibration.iter().enumerate() {
+            let bytes = (*value as u16).to_le_bytes();
+            registers[0x88 + 2 * i] = bytes[0];
+            registers[0x89 + 2 * i] = bytes[1];
+        }
+        // adc_P = 415148, adc_T = 519888
+        registers[0xF7..0xFD].copy_from_slice(&[0x65, 0x5A, 0xC0, 0x7E, 0xED, 0x00]);
+        FakeBmp280 { registers }
+    }
+}
+
+impl Transport for FakeBmp280 {
+    fn write(&mut self, buf: &[u8]) -> Result<(), Error> {
+        self.registers[buf[0] as usize] = buf[1];
+        Ok(())
+    }
+
+    fn write_read(
+        &mut self,
+        buf: &[u8],
+        _delay: Duration,
+        out_buf: &mut [u8],
+    ) -> Result<usize, Error> {
+        let start = buf[0] as usize;
+        out_buf.copy_from_slice(&self.registers[start..start + out_buf.len()]);
+        Ok(out_buf.len())
+    }
+}
+
+#[test]
+fn test_bmp280_datasheet_example() {
+    let mut barometer = Bmp280::from_transport(FakeBmp280::new(0x58)).unwrap();
+    assert!(!barometer.is_bme280());
+
+    let (temperature, pressure) = barometer.read().unwrap();
+    assert!((temperature - 25.08).abs() < 0.01, "{}", temperature);
+    assert!((pressure - 1006.5327).abs() < 0.01, "{}", pressure);
+    assert_eq!(pressure, barometer.pressure().unwrap());
+}
+
+#[test]
+fn test_bmp280_starts_normal_mode() {
+    let barometer = Bmp280::from_transport(FakeBmp280::new(0x60)).unwrap();
+    assert!(barometer.is_bme280());
+    assert_eq!(0x2F, barometer.into_transport().registers[0xF4]);
+}
+
+#[test]
+fn test_bmp280_rejects_unknown_chip() {
+    match Bmp280::from_transport(FakeBmp280::new(0x61)) {
+        Err(Error::InvalidData(_)) => {}
+        other => panic!("unexpected {:?}", other.map(|_| ())),
+    }
+}
+
+#[test]
+fn test_bmp280_without_calibration() {
+    let mut fake = FakeBmp280::new(0x58);
+    for register in &mut fake.registers[0x88..0xA0] {
+        *register = 0;
+    }
+    let mut barometer = Bmp280::from_transport(fake).unwrap();
+    assert!(barometer.pressure().is_err());
+}
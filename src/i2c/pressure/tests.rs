@@ -0,0 +1,119 @@
+/*
+MIT License
+
+Copyright (c) 2021 Crispin Tschirky <ct@fhr.ch>
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+ */
+
+use crate::i2c::pressure::{Barometer, PressureCompensator};
+use crate::i2c::sim::testing::simulated_sensor;
+use crate::i2c::Error;
+use std::time::Duration;
+
+struct FixedBarometer(f32);
+
+impl Barometer for FixedBarometer {
+    fn pressure(&mut self) -> Result<f32, Error> {
+        Ok(self.0)
+    }
+}
+
+#[test]
+fn test_compensates_uncompensated_sensor() {
+    let (mut sensor, sim, _) = simulated_sensor();
+    sensor.start().unwrap();
+    let mut compensator = PressureCompensator::new(FixedBarometer(963.4));
+
+    assert_eq!(Some(963), compensator.update(&mut sensor).unwrap());
+    assert_eq!(Some(963), sim.measuring());
+    assert_eq!(963, sensor.pressure_compensation());
+    assert_eq!(Some(963.4), compensator.last_pressure());
+}
+
+#[test]
+fn test_ignores_stopped_sensor() {
+    let (mut sensor, sim, _) = simulated_sensor();
+    let mut compensator = PressureCompensator::new(FixedBarometer(963.4));
+
+    assert_eq!(None, compensator.update(&mut sensor).unwrap());
+    assert_eq!(None, sim.measuring());
+    assert_eq!(None, compensator.last_pressure());
+}
+
+#[test]
+fn test_threshold() {
+    let (mut sensor, sim, clock) = simulated_sensor();
+    sensor.start_with_alt_comp(1000).unwrap();
+    let mut compensator = PressureCompensator::new(FixedBarometer(1001.5));
+    compensator.set_min_interval(Duration::from_secs(0));
+    clock.advance(Duration::from_secs(60));
+
+    assert_eq!(None, compensator.update(&mut sensor).unwrap());
+    assert_eq!(Some(1000), sim.measuring());
+
+    compensator.barometer_mut().0 = 1002.0;
+    assert_eq!(Some(1002), compensator.update(&mut sensor).unwrap());
+    assert_eq!(Some(1002), sim.measuring());
+}
+
+#[test]
+fn test_rate_limit() {
+    let (mut sensor, sim, clock) = simulated_sensor();
+    sensor.start().unwrap();
+    let mut compensator = PressureCompensator::new(FixedBarometer(980.0));
+    compensator.set_min_interval(Duration::from_secs(600));
+    assert_eq!(Some(980), compensator.update(&mut sensor).unwrap());
+
+    compensator.barometer_mut().0 = 990.0;
+    clock.advance(Duration::from_secs(599));
+    assert_eq!(None, compensator.update(&mut sensor).unwrap());
+    assert_eq!(Some(980), sim.measuring());
+
+    clock.advance(Duration::from_secs(1));
+    assert_eq!(Some(990), compensator.update(&mut sensor).unwrap());
+    assert_eq!(Some(990), sim.measuring());
+}
+
+#[test]
+fn test_update_flags_stabilizing() {
+    let (mut sensor, _, clock) = simulated_sensor();
+    sensor.start().unwrap();
+    clock.advance(Duration::from_secs(60));
+    assert!(!sensor.is_stabilizing());
+
+    let mut compensator = PressureCompensator::new(FixedBarometer(980.0));
+    compensator.update(&mut sensor).unwrap();
+    assert!(sensor.is_stabilizing());
+}
+
+#[test]
+fn test_rejects_pressure_out_of_range() {
+    let (mut sensor, sim, _) = simulated_sensor();
+    sensor.start().unwrap();
+
+    for pressure in &[650.0, 1450.0, f32::NAN] {
+        let mut compensator = PressureCompensator::new(FixedBarometer(*pressure));
+        match compensator.update(&mut sensor) {
+            Err(Error::InvalidData(_)) => {}
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+    assert_eq!(Some(0), sim.measuring());
+}
@@ -0,0 +1,383 @@
+/*
+MIT License
+
+Copyright (c) 2021 Crispin Tschirky <ct@fhr.ch>
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+ */
+
+//! Recording and replay of bus transactions.
+//!
+//! A [`Recorder`] wraps the transport of a sensor and logs every transaction with its time, the
+//! bytes written and read and the result. A [`Replay`] transport feeds such a recording back into
+//! the driver, so a capture taken in the field becomes a reproducible test.
+//!
+//! Each transaction is stored as a line of text:
+//!
+//! ```text
+//! # <elapsed µs> <written> <read> <result>
+//! 5012 0202 - ok
+//! 5020 0202 0001b0 ok
+//! 7031 0300 - nodata No data read
+//! ```
+//!
+//! The read column is `-` for write only transactions and for failed reads, `=` if nothing was
+//! read.
+//!
+//! ```no_run
+//! use scd30pi::i2c::record::{Recorder, Replay};
+//! use scd30pi::i2c::SCD30;
+//! use rppal::i2c::I2c;
+//!
+//! // in the field
+//! let mut i2c = I2c::new().unwrap();
+//! i2c.set_slave_address(0x61).unwrap();
+//! let mut sensor = SCD30::from_transport(Recorder::to_file(i2c, "capture.log").unwrap()).unwrap();
+//! sensor.start().unwrap();
+//!
+//! // in the test
+//! let mut sensor = SCD30::from_transport(Replay::from_file("capture.log").unwrap()).unwrap();
+//! sensor.start().unwrap();
+//! ```
+
+use crate::clock::{default_clock, Clock, ManualClock};
+use crate::i2c::{Error, Transport};
+use log::warn;
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Result of a recorded transaction.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Outcome {
+    /// The transaction succeeded
+    Ok,
+    /// The bus reported an error
+    I2c(String),
+    /// No data could be read
+    NoData(String),
+    /// Any other error
+    Other(String),
+}
+
+impl Outcome {
+    fn from_result<T>(result: &Result<T, Error>) -> Outcome {
+        match result {
+            Ok(_) => Outcome::Ok,
+            Err(Error::I2c(e)) => Outcome::I2c(e.to_string()),
+            Err(Error::NoData(s)) => Outcome::NoData(s.clone()),
+            Err(e) => Outcome::Other(e.to_string()),
+        }
+    }
+
+    /// Gets the error the outcome stands for.
+    fn to_result(&self) -> Result<(), Error> {
+        match self {
+            Outcome::Ok => Ok(()),
+            Outcome::I2c(s) => Err(Error::I2c(rppal::i2c::Error::Io(io::Error::other(
+                s.clone(),
+            )))),
+            Outcome::NoData(s) => Err(Error::NoData(s.clone())),
+            Outcome::Other(s) => Err(Error::NoData(s.clone())),
+        }
+    }
+}
+
+/// A recorded bus transaction.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BusEvent {
+    /// time since the start of the recording
+    pub elapsed: Duration,
+    /// bytes written to the sensor
+    pub written: Vec<u8>,
+    /// bytes read from the sensor, `None` for write only transactions
+    pub read: Option<Vec<u8>>,
+    /// result of the transaction
+    pub outcome: Outcome,
+}
+
+impl fmt::Display for BusEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} ", self.elapsed.as_micros(), to_hex(&self.written))?;
+        match &self.read {
+            Some(read) if !read.is_empty() => write!(f, "{} ", to_hex(read))?,
+            Some(_) => write!(f, "= ")?,
+            None => write!(f, "- ")?,
+        }
+        match &self.outcome {
+            Outcome::Ok => write!(f, "ok"),
+            Outcome::I2c(s) => write!(f, "i2c {}", s),
+            Outcome::NoData(s) => write!(f, "nodata {}", s),
+            Outcome::Other(s) => write!(f, "other {}", s),
+        }
+    }
+}
+
+impl BusEvent {
+    /// Parses an event from a line as written by the recorder.
+    pub fn parse(line: &str) -> Result<BusEvent, Error> {
+        let invalid = || Error::Config(format!("invalid recording line '{}'", line));
+        let mut parts = line.trim().splitn(4, ' ');
+        let elapsed = parts
+            .next()
+            .and_then(|s| s.parse::<u64>().ok())
+            .ok_or_else(invalid)?;
+        let written = parts.next().and_then(from_hex).ok_or_else(invalid)?;
+        let read = match parts.next().ok_or_else(invalid)? {
+            "-" => None,
+            "=" => Some(Vec::new()),
+            s => Some(from_hex(s).ok_or_else(invalid)?),
+        };
+        let result = parts.next().ok_or_else(invalid)?;
+        let mut result_parts = result.splitn(2, ' ');
+        let kind = result_parts.next().unwrap_or("");
+        let message = result_parts.next().unwrap_or("").to_string();
+        let outcome = match kind {
+            "ok" => Outcome::Ok,
+            "i2c" => Outcome::I2c(message),
+            "nodata" => Outcome::NoData(message),
+            "other" => Outcome::Other(message),
+            _ => return Err(invalid()),
+        };
+        Ok(BusEvent {
+            elapsed: Duration::from_micros(elapsed),
+            written,
+            read,
+            outcome,
+        })
+    }
+}
+
+/// Parses a whole recording, skipping empty lines and comments.
+pub fn parse_recording(content: &str) -> Result<Vec<BusEvent>, Error> {
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(BusEvent::parse)
+        .collect()
+}
+
+/// Transport logging every transaction of the wrapped transport.
+pub struct Recorder<T: Transport> {
+    inner: T,
+    clock: Arc<dyn Clock>,
+    start: Instant,
+    /// destination of the log, the events are kept in memory if not set
+    out: Option<Box<dyn Write + Send>>,
+    events: Vec<BusEvent>,
+}
+
+impl<T: Transport> Recorder<T> {
+    /// Records the transactions in memory, see [`Recorder::events`].
+    pub fn new(inner: T) -> Recorder<T> {
+        let clock = default_clock();
+        Recorder {
+            inner,
+            start: clock.now(),
+            clock,
+            out: None,
+            events: Vec::new(),
+        }
+    }
+
+    /// Records the transactions to the given file.
+    pub fn to_file<P: AsRef<Path>>(inner: T, path: P) -> Result<Recorder<T>, Error> {
+        let file = BufWriter::new(File::create(path)?);
+        Ok(Recorder::to_writer(inner, Box::new(file)))
+    }
+
+    /// Records the transactions to the given writer.
+    pub fn to_writer(inner: T, mut out: Box<dyn Write + Send>) -> Recorder<T> {
+        if let Err(e) = writeln!(out, "# <elapsed µs> <written> <read> <result>") {
+            warn!("Cannot write recording: {}", e);
+        }
+        let mut recorder = Recorder::new(inner);
+        recorder.out = Some(out);
+        recorder
+    }
+
+    /// Replaces the time source of the timestamps. Restarts the elapsed time.
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.start = clock.now();
+        self.clock = clock;
+    }
+
+    /// Gets the transactions recorded in memory
+    pub fn events(&self) -> &[BusEvent] {
+        &self.events
+    }
+
+    /// Gets the wrapped transport back
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    fn record(&mut self, event: BusEvent) {
+        match self.out.as_mut() {
+            Some(out) => {
+                if let Err(e) = writeln!(out, "{}", event).and_then(|_| out.flush()) {
+                    warn!("Cannot write recording: {}", e);
+                }
+            }
+            None => self.events.push(event),
+        }
+    }
+}
+
+impl<T: Transport> Transport for Recorder<T> {
+    fn write(&mut self, buf: &[u8]) -> Result<(), Error> {
+        let elapsed = self.clock.now() - self.start;
+        let res = self.inner.write(buf);
+        self.record(BusEvent {
+            elapsed,
+            written: buf.to_vec(),
+            read: None,
+            outcome: Outcome::from_result(&res),
+        });
+        res
+    }
+
+    fn write_read(
+        &mut self,
+        buf: &[u8],
+        delay: Duration,
+        out_buf: &mut [u8],
+    ) -> Result<usize, Error> {
+        let elapsed = self.clock.now() - self.start;
+        let res = self.inner.write_read(buf, delay, out_buf);
+        let read = match res {
+            Ok(count) => Some(out_buf[..count.min(out_buf.len())].to_vec()),
+            Err(_) => None,
+        };
+        self.record(BusEvent {
+            elapsed,
+            written: buf.to_vec(),
+            read,
+            outcome: Outcome::from_result(&res),
+        });
+        res
+    }
+
+    fn clock_speed(&self) -> Result<u32, Error> {
+        self.inner.clock_speed()
+    }
+}
+
+/// Transport answering with the responses of a recording. Every transaction must write the same
+/// bytes as recorded, otherwise it fails with [`Error::NoData`].
+pub struct Replay {
+    events: VecDeque<BusEvent>,
+    /// clock advanced to the time of each replayed transaction
+    clock: Option<(ManualClock, Instant)>,
+}
+
+impl Replay {
+    /// Replays the given transactions
+    pub fn from_events(events: Vec<BusEvent>) -> Replay {
+        Replay {
+            events: events.into(),
+            clock: None,
+        }
+    }
+
+    /// Replays a recording file
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Replay, Error> {
+        let content = std::fs::read_to_string(path)?;
+        Ok(Replay::from_events(parse_recording(&content)?))
+    }
+
+    /// Advances the given clock to the recorded time of each transaction, so the driver sees the
+    /// same timing as during the recording.
+    pub fn set_clock(&mut self, clock: ManualClock) {
+        let start = clock.now();
+        self.clock = Some((clock, start));
+    }
+
+    /// Gets the number of transactions not yet replayed
+    pub fn remaining(&self) -> usize {
+        self.events.len()
+    }
+
+    /// Takes the next event and checks it against the transaction performed by the driver.
+    fn next_event(&mut self, buf: &[u8], with_read: bool) -> Result<BusEvent, Error> {
+        let event = self
+            .events
+            .pop_front()
+            .ok_or_else(|| Error::NoData("Replay exhausted".to_string()))?;
+        if event.written != buf
+            || (event.read.is_some() != with_read && event.outcome == Outcome::Ok)
+        {
+            return Err(Error::NoData(format!(
+                "Replay mismatch: expected {}, got {}",
+                to_hex(&event.written),
+                to_hex(buf)
+            )));
+        }
+        if let Some((clock, start)) = self.clock.as_ref() {
+            let target = *start + event.elapsed;
+            let now = clock.now();
+            if target > now {
+                clock.advance(target - now);
+            }
+        }
+        Ok(event)
+    }
+}
+
+impl Transport for Replay {
+    fn write(&mut self, buf: &[u8]) -> Result<(), Error> {
+        self.next_event(buf, false)?.outcome.to_result()
+    }
+
+    fn write_read(
+        &mut self,
+        buf: &[u8],
+        _delay: Duration,
+        out_buf: &mut [u8],
+    ) -> Result<usize, Error> {
+        let event = self.next_event(buf, true)?;
+        event.outcome.to_result()?;
+        let read = event.read.unwrap_or_default();
+        let count = read.len().min(out_buf.len());
+        out_buf[..count].copy_from_slice(&read[..count]);
+        Ok(count)
+    }
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn from_hex(s: &str) -> Option<Vec<u8>> {
+    if s.len() % 2 != 0 || !s.is_ascii() {
+        return None;
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&s[i..i + 2], 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests;
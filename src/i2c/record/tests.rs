@@ -0,0 +1,130 @@
+/*
+MIT License
+
+Copyright (c) 2021 Crispin Tschirky <ct@fhr.ch>
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+ */
+
+use crate::clock::ManualClock;
+use crate::i2c::record::{parse_recording, BusEvent, Outcome, Recorder, Replay};
+use crate::i2c::sim::{Fault, SimulatedSCD30};
+use crate::i2c::{Error, SCD30};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Runs a short session against a simulated sensor and returns the recorded transactions.
+fn record_session() -> (Vec<BusEvent>, f32) {
+    let clock = ManualClock::new();
+    let sim = SimulatedSCD30::new(Arc::new(clock.clone()));
+    sim.set_environment(734.0, 21.0, 48.0);
+    let mut recorder = Recorder::new(sim.clone());
+    recorder.set_clock(Arc::new(clock.clone()));
+    let mut sensor = SCD30::from_transport(recorder).unwrap();
+    sensor.set_clock(Arc::new(clock.clone()));
+
+    sensor.start().unwrap();
+    clock.advance(Duration::from_secs(2));
+    let co2 = sensor.co2().unwrap();
+    sim.inject_fault(Fault::Nack, Some(1));
+    assert!(sensor.stop().is_err());
+
+    let recorder = sensor.into_transport();
+    (recorder.events().to_vec(), co2)
+}
+
+#[test]
+fn test_recorder_records_transactions() {
+    let (events, _) = record_session();
+    // interval, start, data ready, measurement, stop
+    assert_eq!(5, events.len());
+    assert_eq!(vec![0x46, 0x00], events[0].written);
+    assert_eq!(None, events[1].read);
+    assert_eq!(Some(vec![0x00, 0x01, 0xb0]), events[2].read);
+    assert_eq!(Duration::from_secs(2), events[2].elapsed);
+    assert_eq!(18, events[3].read.as_ref().unwrap().len());
+    match events[4].outcome {
+        Outcome::I2c(_) => {}
+        ref other => panic!("unexpected outcome {:?}", other),
+    }
+}
+
+#[test]
+fn test_recording_text_round_trip() {
+    let (events, _) = record_session();
+    let text: String = events.iter().map(|e| format!("{}\n", e)).collect();
+    assert_eq!(events, parse_recording(&text).unwrap());
+}
+
+#[test]
+fn test_replay_reproduces_session() {
+    let (events, co2) = record_session();
+    let clock = ManualClock::new();
+    let mut replay = Replay::from_events(events);
+    replay.set_clock(clock.clone());
+    let mut sensor = SCD30::from_transport(replay).unwrap();
+    sensor.set_clock(Arc::new(clock));
+
+    sensor.start().unwrap();
+    assert_eq!(co2, sensor.co2().unwrap());
+    match sensor.stop() {
+        Err(Error::I2c(_)) => {}
+        other => panic!("unexpected result {:?}", other),
+    }
+    assert_eq!(0, sensor.into_transport().remaining());
+}
+
+#[test]
+fn test_replay_mismatch() {
+    let (events, _) = record_session();
+    let mut sensor = SCD30::from_transport(Replay::from_events(events)).unwrap();
+    match sensor.stop() {
+        Err(Error::NoData(s)) => assert!(s.contains("mismatch")),
+        other => panic!("unexpected result {:?}", other),
+    }
+}
+
+#[test]
+fn test_replay_exhausted() {
+    let (events, _) = record_session();
+    let mut sensor = SCD30::from_transport(Replay::from_events(events[..1].to_vec())).unwrap();
+    assert!(sensor.start().is_err());
+}
+
+#[test]
+fn test_recorder_file_and_replay_file() {
+    let path = std::env::temp_dir().join(format!("scd30pi-recording-{}.log", std::process::id()));
+    let clock = ManualClock::new();
+    let sim = SimulatedSCD30::new(Arc::new(clock.clone()));
+    let recorder = Recorder::to_file(sim, &path).unwrap();
+    let mut sensor = SCD30::from_transport(recorder).unwrap();
+    let version = sensor.read_firmware_version().unwrap();
+    drop(sensor);
+
+    let mut replayed = SCD30::from_transport(Replay::from_file(&path).unwrap()).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(version, replayed.read_firmware_version().unwrap());
+}
+
+#[test]
+fn test_parse_invalid_line() {
+    assert!(BusEvent::parse("12 0202").is_err());
+    assert!(BusEvent::parse("12 02x2 - ok").is_err());
+    assert!(BusEvent::parse("12 0202 - maybe").is_err());
+}
@@ -0,0 +1,485 @@
+/*
+MIT License
+
+Copyright (c) 2021 Crispin Tschirky <ct@fhr.ch>
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+ */
+
+//! Software emulation of an SCD30 sensor.
+//!
+//! [`SimulatedSCD30`] implements [`Transport`] and speaks the same byte protocol as the real sensor:
+//! word commands with CRC8 protected arguments and responses, the data ready flag raised once per
+//! measurement interval, settings kept over a reset like in the non-volatile memory of the sensor.
+//! This allows to run the [`SCD30`] driver end to end without hardware.
+//!
+//! The simulation shares the [`Clock`] with the driver, so time only passes when the test advances a
+//! [`crate::clock::ManualClock`]. The environment can follow a [`Scenario`] over time and
+//! [`Fault`]s can be injected to exercise the error handling of applications and the driver.
+//!
+//! ```
+//! use scd30pi::clock::ManualClock;
+//! use scd30pi::i2c::sim::SimulatedSCD30;
+//! use scd30pi::i2c::SCD30;
+//! use std::sync::Arc;
+//! use std::time::Duration;
+//!
+//! let clock = ManualClock::new();
+//! let sim = SimulatedSCD30::new(Arc::new(clock.clone()));
+//! sim.set_environment(650.0, 22.5, 40.0);
+//!
+//! let mut sensor = SCD30::from_transport(sim.clone()).unwrap();
+//! sensor.set_clock(Arc::new(clock.clone()));
+//! sensor.start().unwrap();
+//!
+//! clock.advance(Duration::from_secs(2));
+//! assert!(sensor.data_available().unwrap());
+//! assert_eq!(650.0, sensor.co2().unwrap());
+//! ```
+//!
+//! [`SCD30`]: crate::i2c::SCD30
+
+use crate::clock::Clock;
+use crate::i2c::{
+    calculate_crc8, Error, Transport, CMD_AUTOMATIC_SELF_CALIBRATION, CMD_GET_DATA_READY,
+    CMD_GET_FIRMWARE_VERSION, CMD_GET_MEASUREMENT, CMD_RESET, CMD_SET_ALTITUDE_COMPENSATION,
+    CMD_SET_FORCED_RECALIBRATION_FACTOR, CMD_SET_MEASUREMENT_INTERVAL, CMD_SET_TEMPERATURE_OFFSET,
+    CMD_START_CONTINUOUS_MEASUREMENT, CMD_STOP_CONTINUOUS_MEASUREMENT, FORCED_RECALIBRATION_RANGE,
+    MEASURE_INTERVAL_RANGE, PRESSURE_COMPENSATION_RANGE,
+};
+use log::trace;
+use std::io;
+
+pub mod fault;
+pub mod scenario;
+
+pub use self::fault::Fault;
+use self::fault::FaultRule;
+pub use self::scenario::{Profile, Scenario};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+/// Firmware version reported by default, 3.66
+const DEFAULT_FIRMWARE_VERSION: u16 = 0x0342;
+/// errno returned by the Linux i2c-dev driver if the slave does not acknowledge
+const EREMOTEIO: i32 = 121;
+
+/// Settings kept in the non-volatile memory of the sensor.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Persisted {
+    measure_interval: u16,
+    self_calibration: bool,
+    forced_recalibration: u16,
+    temperature_offset_ticks: u16,
+    altitude: u16,
+    /// pressure in mbar the measurement runs with, `None` if stopped
+    measuring: Option<u16>,
+}
+
+/// Complete state of a simulated sensor.
+struct SimState {
+    clock: Arc<dyn Clock>,
+    firmware_version: u16,
+    persisted: Persisted,
+    /// start of the current measurement run
+    started_at: Option<Instant>,
+    /// number of the last sample read since the start
+    read_samples: u64,
+    /// command written without argument waiting to be read
+    pending_read: Option<u16>,
+    /// true environment around the sensor
+    scenario: Scenario,
+    /// start time of the scenario
+    scenario_start: Instant,
+    /// error of the CO2 reading in ppm, corrected by a forced recalibration
+    co2_bias: f32,
+    /// injected faults
+    faults: Vec<FaultRule>,
+}
+
+impl SimState {
+    /// Number of samples measured since the start of the current run
+    fn available_samples(&self) -> u64 {
+        match self.started_at {
+            Some(started_at) => {
+                let interval = u64::from(self.persisted.measure_interval.max(1));
+                let elapsed = self.clock.now().saturating_duration_since(started_at);
+                elapsed.as_secs() / interval
+            }
+            None => 0,
+        }
+    }
+
+    fn data_ready(&self) -> bool {
+        self.available_samples() > self.read_samples
+    }
+
+    /// Time of the last sample available
+    fn sample_time(&self) -> Instant {
+        let interval = Duration::from_secs(u64::from(self.persisted.measure_interval.max(1)));
+        match self.started_at {
+            Some(started_at) => started_at + interval * self.available_samples() as u32,
+            None => self.clock.now(),
+        }
+    }
+
+    /// CO2 concentration in ppm, temperature in °C and humidity in % as reported by the sensor
+    fn reported_values(&self) -> (f32, f32, f32) {
+        let since_start = self
+            .sample_time()
+            .saturating_duration_since(self.scenario_start);
+        let (co2, temperature, humidity) = self.scenario.values_at(since_start);
+        (
+            (co2 + self.co2_bias).max(0.0),
+            temperature - self.persisted.temperature_offset_ticks as f32 / 100.0,
+            humidity,
+        )
+    }
+
+    /// Checks the injected faults for the given one and counts it as applied.
+    fn take_fault<F: Fn(&Fault) -> bool>(&mut self, matches: F) -> Option<Fault> {
+        let now = self.clock.now();
+        let rule = self
+            .faults
+            .iter_mut()
+            .find(|rule| rule.is_active(now) && matches(&rule.fault))?;
+        rule.consume();
+        Some(rule.fault)
+    }
+
+    /// Fails the transaction if the sensor does not acknowledge.
+    fn check_acknowledge(&mut self) -> Result<(), Error> {
+        match self.take_fault(|f| *f == Fault::Nack || *f == Fault::Vanish) {
+            Some(fault) => Err(nack(&format!("{:?}", fault))),
+            None => Ok(()),
+        }
+    }
+
+    fn start(&mut self, pressure_mbar: u16) {
+        self.persisted.measuring = Some(pressure_mbar);
+        self.started_at = Some(self.clock.now());
+        self.read_samples = 0;
+    }
+
+    /// Restarts the sensor, only the persisted settings survive.
+    fn restart(&mut self) {
+        self.pending_read = None;
+        self.started_at = None;
+        self.read_samples = 0;
+        if let Some(pressure_mbar) = self.persisted.measuring {
+            self.start(pressure_mbar);
+        }
+    }
+
+    /// Executes a write transaction
+    fn write(&mut self, buf: &[u8]) -> Result<(), Error> {
+        self.pending_read = None;
+        if buf.len() != 2 && buf.len() != 5 {
+            return Err(nack(&format!("invalid write length {}", buf.len())));
+        }
+        let command = ((buf[0] as u16) << 8) | buf[1] as u16;
+        if buf.len() == 2 {
+            return self.execute(command);
+        }
+        if calculate_crc8(&buf[2..5]) != 0 {
+            return Err(nack("invalid argument CRC"));
+        }
+        let argument = ((buf[2] as u16) << 8) | buf[3] as u16;
+        self.execute_with_argument(command, argument)
+    }
+
+    /// Executes a command without argument
+    fn execute(&mut self, command: u16) -> Result<(), Error> {
+        match command {
+            CMD_STOP_CONTINUOUS_MEASUREMENT => {
+                self.persisted.measuring = None;
+                self.started_at = None;
+            }
+            CMD_RESET => self.restart(),
+            CMD_SET_MEASUREMENT_INTERVAL
+            | CMD_GET_DATA_READY
+            | CMD_GET_MEASUREMENT
+            | CMD_AUTOMATIC_SELF_CALIBRATION
+            | CMD_SET_FORCED_RECALIBRATION_FACTOR
+            | CMD_SET_TEMPERATURE_OFFSET
+            | CMD_SET_ALTITUDE_COMPENSATION
+            | CMD_GET_FIRMWARE_VERSION => self.pending_read = Some(command),
+            _ => return Err(nack(&format!("unknown command {:#06x}", command))),
+        }
+        Ok(())
+    }
+
+    /// Executes a command with argument
+    fn execute_with_argument(&mut self, command: u16, argument: u16) -> Result<(), Error> {
+        match command {
+            CMD_START_CONTINUOUS_MEASUREMENT
+                if argument == 0 || PRESSURE_COMPENSATION_RANGE.contains(&argument) =>
+            {
+                self.start(argument)
+            }
+            CMD_SET_MEASUREMENT_INTERVAL if MEASURE_INTERVAL_RANGE.contains(&argument) => {
+                self.persisted.measure_interval = argument;
+                if self.started_at.is_some() {
+                    self.started_at = Some(self.clock.now());
+                    self.read_samples = 0;
+                }
+            }
+            CMD_AUTOMATIC_SELF_CALIBRATION if argument <= 1 => {
+                self.persisted.self_calibration = argument == 1
+            }
+            CMD_SET_FORCED_RECALIBRATION_FACTOR
+                if FORCED_RECALIBRATION_RANGE.contains(&argument) =>
+            {
+                self.co2_bias += argument as f32 - self.reported_values().0;
+                self.persisted.forced_recalibration = argument;
+            }
+            CMD_SET_TEMPERATURE_OFFSET => self.persisted.temperature_offset_ticks = argument,
+            CMD_SET_ALTITUDE_COMPENSATION => self.persisted.altitude = argument,
+            _ => {
+                return Err(nack(&format!(
+                    "invalid command {:#06x} with argument {}",
+                    command, argument
+                )))
+            }
+        }
+        Ok(())
+    }
+
+    /// Builds the response of the pending read command
+    fn response(&mut self) -> Result<Vec<u8>, Error> {
+        let command = match self.pending_read.take() {
+            Some(command) => command,
+            None => return Err(Error::NoData("No data read".to_string())),
+        };
+        let mut response = Vec::with_capacity(18);
+        match command {
+            CMD_GET_MEASUREMENT => {
+                if !self.data_ready() {
+                    return Err(Error::NoData("No data read".to_string()));
+                }
+                self.read_samples = self.available_samples();
+                let (co2, temperature, humidity) = self.reported_values();
+                for value in &[co2, temperature, humidity] {
+                    response.extend_from_slice(&encode_measure_value(*value));
+                }
+            }
+            _ => {
+                let word = match command {
+                    CMD_SET_MEASUREMENT_INTERVAL => self.persisted.measure_interval,
+                    CMD_GET_DATA_READY => {
+                        match self.take_fault(|f| matches!(f, Fault::StuckDataReady(_))) {
+                            Some(Fault::StuckDataReady(ready)) => ready as u16,
+                            _ => self.data_ready() as u16,
+                        }
+                    }
+                    CMD_AUTOMATIC_SELF_CALIBRATION => self.persisted.self_calibration as u16,
+                    CMD_SET_FORCED_RECALIBRATION_FACTOR => self.persisted.forced_recalibration,
+                    CMD_SET_TEMPERATURE_OFFSET => self.persisted.temperature_offset_ticks,
+                    CMD_SET_ALTITUDE_COMPENSATION => self.persisted.altitude,
+                    _ => self.firmware_version,
+                };
+                response.extend_from_slice(&encode_word(word));
+            }
+        }
+        if self.take_fault(|f| *f == Fault::CorruptCrc).is_some() {
+            let last = response.len() - 1;
+            response[last] ^= 0xff;
+        }
+        if let Some(Fault::ShortRead(count)) = self.take_fault(|f| matches!(f, Fault::ShortRead(_)))
+        {
+            response.truncate(count);
+        }
+        trace!("Simulated response {:#06x}: {:x?}", command, response);
+        Ok(response)
+    }
+}
+
+/// Simulated sensor usable as transport of the [`crate::i2c::SCD30`] driver. Clones share the same
+/// sensor, so a test can keep a handle to change the environment while the driver owns another.
+#[derive(Clone)]
+pub struct SimulatedSCD30 {
+    state: Arc<Mutex<SimState>>,
+}
+
+impl SimulatedSCD30 {
+    /// Creates a stopped sensor with factory settings using the given clock.
+    pub fn new(clock: Arc<dyn Clock>) -> SimulatedSCD30 {
+        let now = clock.now();
+        SimulatedSCD30 {
+            state: Arc::new(Mutex::new(SimState {
+                clock,
+                firmware_version: DEFAULT_FIRMWARE_VERSION,
+                persisted: Persisted {
+                    measure_interval: 2,
+                    self_calibration: false,
+                    forced_recalibration: 400,
+                    temperature_offset_ticks: 0,
+                    altitude: 0,
+                    measuring: None,
+                },
+                started_at: None,
+                read_samples: 0,
+                pending_read: None,
+                scenario: Scenario::constant(400.0, 20.0, 50.0),
+                scenario_start: now,
+                co2_bias: 0.0,
+                faults: Vec::new(),
+            })),
+        }
+    }
+
+    fn state(&self) -> MutexGuard<'_, SimState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Sets the true CO2 concentration in ppm, temperature in °C and relative humidity in %
+    /// around the sensor.
+    pub fn set_environment(&self, co2: f32, temperature: f32, humidity: f32) {
+        self.run_scenario(Scenario::constant(co2, temperature, humidity));
+    }
+
+    /// Lets the environment around the sensor follow the given scenario starting now.
+    pub fn run_scenario(&self, scenario: Scenario) {
+        let mut state = self.state();
+        state.scenario_start = state.clock.now();
+        state.scenario = scenario;
+    }
+
+    /// Injects a fault affecting the given number of transactions, `None` until the faults are
+    /// cleared.
+    pub fn inject_fault(&self, fault: Fault, times: Option<u32>) {
+        self.schedule_fault(Duration::from_secs(0), fault, times);
+    }
+
+    /// Injects a fault which becomes active after the given delay and then affects the given number
+    /// of transactions, `None` until the faults are cleared.
+    pub fn schedule_fault(&self, delay: Duration, fault: Fault, times: Option<u32>) {
+        let mut state = self.state();
+        let active_from = state.clock.now() + delay;
+        state.faults.push(FaultRule {
+            fault,
+            active_from,
+            remaining: times,
+        });
+    }
+
+    /// Removes all injected faults.
+    pub fn clear_faults(&self) {
+        self.state().faults.clear();
+    }
+
+    /// Sets the error of the CO2 reading in ppm which a forced recalibration corrects.
+    pub fn set_co2_bias(&self, co2_bias: f32) {
+        self.state().co2_bias = co2_bias;
+    }
+
+    /// Sets the firmware version reported by the sensor, e.g. 0x0342 for 3.66
+    pub fn set_firmware_version(&self, version: u16) {
+        self.state().firmware_version = version;
+    }
+
+    /// Cuts and restores the power supply. Only the persisted settings survive.
+    pub fn power_cycle(&self) {
+        self.state().restart();
+    }
+
+    /// Gets the ambient pressure the continuous measurement runs with, `None` if stopped.
+    pub fn measuring(&self) -> Option<u16> {
+        self.state().persisted.measuring
+    }
+
+    /// Gets the measure interval in seconds
+    pub fn measure_interval(&self) -> u16 {
+        self.state().persisted.measure_interval
+    }
+
+    /// True if the automatic self calibration is enabled
+    pub fn self_calibration(&self) -> bool {
+        self.state().persisted.self_calibration
+    }
+
+    /// Gets the last forced recalibration value in ppm
+    pub fn forced_recalibration(&self) -> u16 {
+        self.state().persisted.forced_recalibration
+    }
+
+    /// Gets the temperature offset in °C
+    pub fn temperature_offset(&self) -> f32 {
+        self.state().persisted.temperature_offset_ticks as f32 / 100.0
+    }
+
+    /// Gets the altitude compensation in meters above sea level
+    pub fn altitude(&self) -> u16 {
+        self.state().persisted.altitude
+    }
+}
+
+impl Transport for SimulatedSCD30 {
+    fn write(&mut self, buf: &[u8]) -> Result<(), Error> {
+        let mut state = self.state();
+        state.check_acknowledge()?;
+        state.write(buf)
+    }
+
+    fn write_read(
+        &mut self,
+        buf: &[u8],
+        _delay: Duration,
+        out_buf: &mut [u8],
+    ) -> Result<usize, Error> {
+        let mut state = self.state();
+        state.check_acknowledge()?;
+        state.write(buf)?;
+        let response = state.response()?;
+        let count = response.len().min(out_buf.len());
+        out_buf[..count].copy_from_slice(&response[..count]);
+        Ok(count)
+    }
+
+    fn clock_speed(&self) -> Result<u32, Error> {
+        Ok(100_000)
+    }
+}
+
+/// Encodes a word followed by its CRC
+fn encode_word(word: u16) -> [u8; 3] {
+    let bytes = [(word >> 8) as u8, (word & 0xff) as u8];
+    [bytes[0], bytes[1], calculate_crc8(&bytes)]
+}
+
+/// Encodes a float as two words each followed by its CRC
+fn encode_measure_value(value: f32) -> [u8; 6] {
+    let bits = value.to_bits();
+    let high = encode_word((bits >> 16) as u16);
+    let low = encode_word((bits & 0xffff) as u16);
+    [high[0], high[1], high[2], low[0], low[1], low[2]]
+}
+
+/// Error as reported by the i2c driver if the sensor does not acknowledge
+fn nack(reason: &str) -> Error {
+    trace!("Simulated NACK: {}", reason);
+    Error::I2c(rppal::i2c::Error::Io(io::Error::from_raw_os_error(
+        EREMOTEIO,
+    )))
+}
+
+#[cfg(test)]
+pub(crate) mod testing;
+#[cfg(test)]
+mod tests;
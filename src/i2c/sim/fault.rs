@@ -0,0 +1,66 @@
+/*
+MIT License
+
+Copyright (c) 2021 Crispin Tschirky <ct@fhr.ch>
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+ */
+
+//! Faults which can be injected into the simulated sensor.
+
+use std::time::Instant;
+
+/// A misbehaviour of the sensor or the bus.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Fault {
+    /// The sensor does not acknowledge the transaction
+    Nack,
+    /// The CRC of the last word of a response is corrupted
+    CorruptCrc,
+    /// Only the given number of bytes of a response is delivered
+    ShortRead(usize),
+    /// The data ready flag keeps the given value regardless of the measurement
+    StuckDataReady(bool),
+    /// The sensor is gone from the bus and does not answer at all
+    Vanish,
+}
+
+/// An injected fault and how often it still applies.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct FaultRule {
+    pub(crate) fault: Fault,
+    /// the fault applies from this time on
+    pub(crate) active_from: Instant,
+    /// number of transactions still affected, `None` for an unlimited number
+    pub(crate) remaining: Option<u32>,
+}
+
+impl FaultRule {
+    /// True if the rule affects a transaction at the given time
+    pub(crate) fn is_active(&self, now: Instant) -> bool {
+        now >= self.active_from && self.remaining != Some(0)
+    }
+
+    /// Counts a transaction affected by the rule.
+    pub(crate) fn consume(&mut self) {
+        if let Some(remaining) = self.remaining.as_mut() {
+            *remaining = remaining.saturating_sub(1);
+        }
+    }
+}
@@ -0,0 +1,227 @@
+/*
+MIT License
+
+Copyright (c) 2021 Crispin Tschirky <ct@fhr.ch>
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+ */
+
+//! Scripted environments for the simulated sensor.
+//!
+//! A [`Profile`] describes how a single quantity evolves over time as a sequence of segments, each
+//! continuing from the value the previous one ended with. A [`Scenario`] combines the profiles of
+//! CO2, temperature and humidity.
+//!
+//! ```
+//! use scd30pi::i2c::sim::scenario::{Profile, Scenario};
+//! use std::time::Duration;
+//!
+//! let minutes = |m: u64| Duration::from_secs(m * 60);
+//! // a class enters, the CO2 rises for 45 minutes, then the window is opened
+//! let co2 = Profile::constant(420.0)
+//!     .hold(minutes(5))
+//!     .ramp(1800.0, minutes(45))
+//!     .approach(450.0, minutes(4), minutes(20));
+//! let scenario = Scenario::new(co2, Profile::constant(21.0), Profile::constant(45.0));
+//! assert_eq!(1800.0, scenario.values_at(minutes(50)).0);
+//! ```
+
+use std::time::Duration;
+
+/// Part of a profile, starting at the value the previous segment ended with.
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    /// keeps the value for the given time
+    Hold(Duration),
+    /// changes the value immediately
+    Step(f32),
+    /// changes the value linearly to the target within the given time
+    Ramp { to: f32, duration: Duration },
+    /// approaches the target exponentially with the given time constant for the given time
+    Approach {
+        to: f32,
+        time_constant: Duration,
+        duration: Duration,
+    },
+}
+
+/// Evolution of a single quantity over time. After the last segment the value is kept.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Profile {
+    start: f32,
+    segments: Vec<Segment>,
+}
+
+impl Profile {
+    /// Creates a profile keeping the given value
+    pub fn constant(value: f32) -> Profile {
+        Profile {
+            start: value,
+            segments: Vec::new(),
+        }
+    }
+
+    /// Keeps the current value for the given time.
+    pub fn hold(mut self, duration: Duration) -> Profile {
+        self.segments.push(Segment::Hold(duration));
+        self
+    }
+
+    /// Changes the value immediately, e.g. when a device is switched on.
+    pub fn step(mut self, to: f32) -> Profile {
+        self.segments.push(Segment::Step(to));
+        self
+    }
+
+    /// Changes the value linearly to `to` within the given time, e.g. while a room fills up.
+    pub fn ramp(mut self, to: f32, duration: Duration) -> Profile {
+        self.segments.push(Segment::Ramp { to, duration });
+        self
+    }
+
+    /// Approaches `to` exponentially with the given time constant for the given time, e.g. while
+    /// a window is open.
+    pub fn approach(mut self, to: f32, time_constant: Duration, duration: Duration) -> Profile {
+        self.segments.push(Segment::Approach {
+            to,
+            time_constant,
+            duration,
+        });
+        self
+    }
+
+    /// Gets the value at the given time since the start of the profile.
+    pub fn value_at(&self, time: Duration) -> f32 {
+        let mut value = self.start;
+        let mut remaining = time.as_secs_f32();
+        for segment in &self.segments {
+            match *segment {
+                Segment::Hold(duration) => remaining -= duration.as_secs_f32(),
+                Segment::Step(to) => value = to,
+                Segment::Ramp { to, duration } => {
+                    let duration = duration.as_secs_f32();
+                    if remaining < duration {
+                        return value + (to - value) * remaining / duration;
+                    }
+                    value = to;
+                    remaining -= duration;
+                }
+                Segment::Approach {
+                    to,
+                    time_constant,
+                    duration,
+                } => {
+                    let tau = time_constant.as_secs_f32().max(f32::EPSILON);
+                    let elapsed = remaining.min(duration.as_secs_f32());
+                    value = to + (value - to) * (-elapsed / tau).exp();
+                    remaining -= duration.as_secs_f32();
+                }
+            }
+            if remaining < 0.0 {
+                break;
+            }
+        }
+        value
+    }
+}
+
+/// Evolution of the environment around a simulated sensor.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Scenario {
+    /// CO2 concentration in ppm
+    pub co2: Profile,
+    /// temperature in °C
+    pub temperature: Profile,
+    /// relative humidity in %
+    pub humidity: Profile,
+}
+
+impl Scenario {
+    /// Creates a scenario from the profiles of CO2 in ppm, temperature in °C and humidity in %
+    pub fn new(co2: Profile, temperature: Profile, humidity: Profile) -> Scenario {
+        Scenario {
+            co2,
+            temperature,
+            humidity,
+        }
+    }
+
+    /// Creates a scenario with a constant environment
+    pub fn constant(co2: f32, temperature: f32, humidity: f32) -> Scenario {
+        Scenario::new(
+            Profile::constant(co2),
+            Profile::constant(temperature),
+            Profile::constant(humidity),
+        )
+    }
+
+    /// Creates a room which is occupied after `empty` and whose CO2 concentration rises linearly
+    /// from `outdoor_co2` to `peak_co2` within `occupied`.
+    pub fn occupancy(
+        outdoor_co2: f32,
+        peak_co2: f32,
+        empty: Duration,
+        occupied: Duration,
+    ) -> Scenario {
+        Scenario::new(
+            Profile::constant(outdoor_co2)
+                .hold(empty)
+                .ramp(peak_co2, occupied),
+            Profile::constant(21.0),
+            Profile::constant(45.0),
+        )
+    }
+
+    /// Creates a room at `indoor_co2` whose window is opened after `closed`. The CO2
+    /// concentration then decays towards `outdoor_co2` with the given time constant while the
+    /// temperature drops towards `outdoor_temperature`.
+    pub fn window_opening(
+        indoor_co2: f32,
+        outdoor_co2: f32,
+        outdoor_temperature: f32,
+        closed: Duration,
+        time_constant: Duration,
+    ) -> Scenario {
+        let open = time_constant * 10;
+        Scenario::new(
+            Profile::constant(indoor_co2)
+                .hold(closed)
+                .approach(outdoor_co2, time_constant, open),
+            Profile::constant(21.0).hold(closed).approach(
+                outdoor_temperature,
+                time_constant * 2,
+                open,
+            ),
+            Profile::constant(45.0),
+        )
+    }
+
+    /// Gets CO2 in ppm, temperature in °C and humidity in % at the given time since the start of
+    /// the scenario.
+    pub fn values_at(&self, time: Duration) -> (f32, f32, f32) {
+        (
+            self.co2.value_at(time),
+            self.temperature.value_at(time),
+            self.humidity.value_at(time),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,84 @@
+/*
+MIT License
+
+Copyright (c) 2021 Crispin Tschirky <ct@fhr.ch>
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+ */
+
+use crate::i2c::sim::scenario::{Profile, Scenario};
+use std::time::Duration;
+
+fn secs(s: u64) -> Duration {
+    Duration::from_secs(s)
+}
+
+#[test]
+fn test_profile_constant() {
+    let profile = Profile::constant(420.0);
+    assert_eq!(420.0, profile.value_at(secs(0)));
+    assert_eq!(420.0, profile.value_at(secs(100_000)));
+}
+
+#[test]
+fn test_profile_hold_and_step() {
+    let profile = Profile::constant(20.0).hold(secs(60)).step(25.0);
+    assert_eq!(20.0, profile.value_at(secs(59)));
+    assert_eq!(25.0, profile.value_at(secs(60)));
+    assert_eq!(25.0, profile.value_at(secs(600)));
+}
+
+#[test]
+fn test_profile_ramp() {
+    let profile = Profile::constant(400.0).ramp(1000.0, secs(600));
+    assert_eq!(400.0, profile.value_at(secs(0)));
+    assert_eq!(700.0, profile.value_at(secs(300)));
+    assert_eq!(1000.0, profile.value_at(secs(600)));
+    assert_eq!(1000.0, profile.value_at(secs(1200)));
+}
+
+#[test]
+fn test_profile_approach() {
+    let profile = Profile::constant(1400.0).approach(400.0, secs(600), secs(3600));
+    let after_tau = profile.value_at(secs(600));
+    assert!((after_tau - (400.0 + 1000.0 / std::f32::consts::E)).abs() < 0.5);
+    // the value reached at the end of the segment is kept
+    assert_eq!(profile.value_at(secs(3600)), profile.value_at(secs(7200)));
+}
+
+#[test]
+fn test_profile_segments_continue() {
+    let profile = Profile::constant(400.0)
+        .ramp(800.0, secs(100))
+        .hold(secs(100))
+        .ramp(600.0, secs(100));
+    assert_eq!(800.0, profile.value_at(secs(150)));
+    assert_eq!(700.0, profile.value_at(secs(250)));
+}
+
+#[test]
+fn test_scenario_window_opening() {
+    let scenario = Scenario::window_opening(1500.0, 420.0, 5.0, secs(60), secs(300));
+    let (co2, temperature, _) = scenario.values_at(secs(60));
+    assert_eq!(1500.0, co2);
+    assert_eq!(21.0, temperature);
+    let (co2, temperature, _) = scenario.values_at(secs(60 + 3000));
+    assert!(co2 < 421.0);
+    assert!(temperature < 7.0);
+}
@@ -0,0 +1,39 @@
+/*
+MIT License
+
+Copyright (c) 2021 Crispin Tschirky <ct@fhr.ch>
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+ */
+
+//! Fixtures shared by the tests of modules driving a [`SimulatedSCD30`].
+
+use crate::clock::ManualClock;
+use crate::i2c::sim::SimulatedSCD30;
+use crate::i2c::SCD30;
+use std::sync::Arc;
+
+/// Creates a sensor talking to a simulated one, both timed by the returned manual clock.
+pub(crate) fn simulated_sensor() -> (SCD30<SimulatedSCD30>, SimulatedSCD30, ManualClock) {
+    let clock = ManualClock::new();
+    let sim = SimulatedSCD30::new(Arc::new(clock.clone()));
+    let mut sensor = SCD30::from_transport(sim.clone()).unwrap();
+    sensor.set_clock(Arc::new(clock.clone()));
+    (sensor, sim, clock)
+}
@@ -0,0 +1,426 @@
+/*
+MIT License
+
+Copyright (c) 2021 Crispin Tschirky <ct@fhr.ch>
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+ */
+
+use crate::clock::Clock;
+use crate::i2c::config::SensorConfig;
+use crate::i2c::sim::testing::simulated_sensor;
+use crate::i2c::sim::{Fault, Profile, Scenario};
+use crate::i2c::{prepare_cmd, prepare_cmd_with_args, Error, FreshnessPolicy, Transport};
+use std::time::Duration;
+
+#[test]
+fn test_sim_firmware_version() {
+    let (mut sensor, _, _) = simulated_sensor();
+    assert_eq!("3.66", sensor.read_firmware_version().unwrap());
+}
+
+#[test]
+fn test_sim_data_ready_follows_interval() {
+    let (mut sensor, _, clock) = simulated_sensor();
+    sensor.set_measure_interval(5).unwrap();
+    sensor.start().unwrap();
+
+    clock.advance(Duration::from_secs(4));
+    assert!(!sensor.data_available().unwrap());
+    clock.advance(Duration::from_secs(1));
+    assert!(sensor.data_available().unwrap());
+}
+
+#[test]
+fn test_sim_no_data_when_stopped() {
+    let (mut sensor, _, clock) = simulated_sensor();
+    clock.advance(Duration::from_secs(10));
+    assert!(!sensor.data_available().unwrap());
+    assert_eq!(None, sensor.measurement().unwrap());
+}
+
+#[test]
+fn test_sim_measurement() {
+    let (mut sensor, sim, clock) = simulated_sensor();
+    sim.set_environment(812.0, 23.25, 41.5);
+    sensor.start().unwrap();
+    clock.advance(Duration::from_secs(2));
+
+    let measurement = sensor.measurement().unwrap().unwrap();
+    assert_eq!(812.0, measurement.co2);
+    assert_eq!(23.25, measurement.temperature);
+    assert_eq!(41.5, measurement.humidity);
+    assert_eq!(clock.now(), measurement.timestamp);
+    assert!(!sensor.data_available().unwrap());
+}
+
+#[test]
+fn test_sim_measurement_cached_until_new_sample() {
+    let (mut sensor, sim, clock) = simulated_sensor();
+    sim.set_environment(500.0, 20.0, 50.0);
+    sensor.start().unwrap();
+    clock.advance(Duration::from_secs(2));
+    assert_eq!(500.0, sensor.co2().unwrap());
+
+    sim.set_environment(900.0, 20.0, 50.0);
+    clock.advance(Duration::from_secs(1));
+    assert_eq!(500.0, sensor.co2().unwrap());
+    clock.advance(Duration::from_secs(1));
+    assert_eq!(900.0, sensor.co2().unwrap());
+}
+
+#[test]
+fn test_sim_freshness_measure_interval_saves_bus_traffic() {
+    let (mut sensor, _, clock) = simulated_sensor();
+    assert_eq!(FreshnessPolicy::MeasureInterval, sensor.freshness_policy());
+    sensor.start().unwrap();
+    clock.advance(Duration::from_secs(2));
+    sensor.co2().unwrap();
+    let commands = sensor.stats().commands;
+
+    // no new sample is due within the measure interval
+    clock.advance(Duration::from_millis(1999));
+    sensor.co2().unwrap();
+    sensor.temperature().unwrap();
+    sensor.humidity().unwrap();
+    assert_eq!(commands, sensor.stats().commands);
+
+    // data ready and the sample
+    clock.advance(Duration::from_millis(1));
+    sensor.co2().unwrap();
+    sensor.temperature().unwrap();
+    assert_eq!(commands + 2, sensor.stats().commands);
+}
+
+#[test]
+fn test_sim_freshness_measure_interval_counts_from_sample() {
+    let (mut sensor, sim, clock) = simulated_sensor();
+    sensor.start().unwrap();
+    let start = clock.now();
+    sim.set_environment(800.0, 21.0, 40.0);
+
+    // the sample of 2 s is read late, the next one is due at 4 s nevertheless
+    clock.advance(Duration::from_millis(3900));
+    let first = sensor.measurement().unwrap().unwrap();
+    assert_eq!(Duration::from_secs(2), first.timestamp - start);
+    assert_eq!(Duration::from_millis(1900), first.age);
+
+    sim.set_environment(900.0, 21.0, 40.0);
+    clock.advance(Duration::from_millis(100));
+    let second = sensor.measurement().unwrap().unwrap();
+    assert_eq!(900.0, second.co2);
+    assert_eq!(Duration::from_secs(4), second.timestamp - start);
+    assert_eq!(Duration::from_secs(0), second.age);
+}
+
+#[test]
+fn test_sim_freshness_never_block_reports_age() {
+    let (mut sensor, _, clock) = simulated_sensor();
+    sensor.set_freshness_policy(FreshnessPolicy::NeverBlock);
+    sensor.start().unwrap();
+    assert_eq!(None, sensor.measurement().unwrap());
+    clock.advance(Duration::from_secs(2));
+    assert_eq!(
+        Duration::from_secs(0),
+        sensor.measurement().unwrap().unwrap().age
+    );
+    clock.advance(Duration::from_millis(1500));
+    assert_eq!(
+        Duration::from_millis(1500),
+        sensor.measurement().unwrap().unwrap().age
+    );
+}
+
+#[test]
+fn test_sim_freshness_wait_for_new() {
+    let (mut sensor, _, clock) = simulated_sensor();
+    sensor.set_freshness_policy(FreshnessPolicy::WaitForNew {
+        timeout: Duration::from_secs(5),
+    });
+    sensor.start().unwrap();
+    let start = clock.now();
+
+    let first = sensor.measurement().unwrap().unwrap();
+    assert_eq!(Duration::from_secs(2), first.timestamp - start);
+    let second = sensor.measurement().unwrap().unwrap();
+    assert_eq!(Duration::from_secs(4), second.timestamp - start);
+    assert_eq!(Duration::from_secs(0), second.age);
+}
+
+#[test]
+fn test_sim_freshness_wait_for_new_timeout() {
+    let (mut sensor, _, clock) = simulated_sensor();
+    sensor.set_freshness_policy(FreshnessPolicy::WaitForNew {
+        timeout: Duration::from_secs(5),
+    });
+    let start = clock.now();
+    match sensor.measurement() {
+        Err(Error::NoData(_)) => {}
+        other => panic!("unexpected result {:?}", other),
+    }
+    assert!(clock.now() - start >= Duration::from_secs(5));
+    assert!(clock.now() - start < Duration::from_secs(6));
+}
+
+#[test]
+fn test_sim_freshness_max_age() {
+    let (mut sensor, sim, clock) = simulated_sensor();
+    sensor.set_freshness_policy(FreshnessPolicy::MaxAge(Duration::from_secs(10)));
+    sim.set_environment(500.0, 20.0, 50.0);
+    sensor.start().unwrap();
+    assert_eq!(500.0, sensor.co2().unwrap());
+
+    // cached values are used while young enough, even if new samples are available
+    sim.set_environment(900.0, 20.0, 50.0);
+    clock.advance(Duration::from_secs(10));
+    assert_eq!(500.0, sensor.co2().unwrap());
+    clock.advance(Duration::from_millis(1));
+    assert_eq!(900.0, sensor.co2().unwrap());
+}
+
+#[test]
+fn test_sim_temperature_offset() {
+    let (mut sensor, sim, clock) = simulated_sensor();
+    sim.set_environment(500.0, 25.0, 50.0);
+    sensor.set_temperature_offset(2.5).unwrap();
+    assert_eq!(2.5, sensor.read_temperature_offset().unwrap());
+    sensor.start().unwrap();
+    clock.advance(Duration::from_secs(2));
+    assert_eq!(22.5, sensor.temperature().unwrap());
+}
+
+#[test]
+fn test_sim_forced_recalibration() {
+    let (mut sensor, sim, clock) = simulated_sensor();
+    sim.set_environment(400.0, 20.0, 50.0);
+    sim.set_co2_bias(75.0);
+    sensor.start().unwrap();
+    clock.advance(Duration::from_secs(2));
+    assert_eq!(475.0, sensor.co2().unwrap());
+
+    sensor.set_forced_recalibration(400).unwrap();
+    assert_eq!(400, sim.forced_recalibration());
+    clock.advance(Duration::from_secs(4));
+    assert_eq!(400.0, sensor.co2().unwrap());
+}
+
+#[test]
+fn test_sim_settings_survive_reset() {
+    let (mut sensor, sim, clock) = simulated_sensor();
+    sensor.set_measure_interval(10).unwrap();
+    sensor.enable_self_calibration().unwrap();
+    sensor.set_altitude_compensation(540).unwrap();
+    sensor.start_with_alt_comp(960).unwrap();
+
+    sensor.soft_reset().unwrap();
+    assert_eq!(10, sensor.read_measure_interval().unwrap());
+    assert!(sensor.read_self_calibration().unwrap());
+    assert_eq!(540, sensor.read_altitude_compensation().unwrap());
+    assert_eq!(Some(960), sim.measuring());
+
+    // the measurement restarts, the first sample is due one interval after the reset
+    clock.advance(Duration::from_secs(9));
+    assert!(!sensor.data_available().unwrap());
+    clock.advance(Duration::from_secs(1));
+    assert!(sensor.data_available().unwrap());
+}
+
+#[test]
+fn test_sim_stop_survives_power_cycle() {
+    let (mut sensor, sim, _) = simulated_sensor();
+    sensor.start().unwrap();
+    sensor.stop().unwrap();
+    sim.power_cycle();
+    assert_eq!(None, sim.measuring());
+}
+
+#[test]
+fn test_sim_config_round_trip() {
+    let (mut sensor, _, _) = simulated_sensor();
+    let config = SensorConfig {
+        firmware_version: "3.66".to_string(),
+        measure_interval: 30,
+        self_calibration: true,
+        altitude_compensation: 420,
+        temperature_offset: 1.5,
+        pressure_compensation: 950,
+    };
+    sensor.apply_config(&config).unwrap();
+
+    let (mut replacement, replacement_sim, _) = simulated_sensor();
+    replacement
+        .apply_config(&sensor.read_config().unwrap())
+        .unwrap();
+    assert_eq!(config, replacement.read_config().unwrap());
+    assert_eq!(Some(950), replacement_sim.measuring());
+}
+
+#[test]
+fn test_sim_rejects_invalid_argument_crc() {
+    let (_, mut sim, _) = simulated_sensor();
+    let mut buf = prepare_cmd_with_args(0x4600, 5);
+    buf[4] ^= 0xff;
+    match sim.write(&buf) {
+        Err(Error::I2c(_)) => {}
+        other => panic!("unexpected result {:?}", other),
+    }
+    assert_eq!(2, sim.measure_interval());
+}
+
+#[test]
+fn test_sim_rejects_unknown_command() {
+    let (_, mut sim, _) = simulated_sensor();
+    assert!(sim.write(&prepare_cmd(0x1234)).is_err());
+}
+
+#[test]
+fn test_sim_rejects_out_of_range_interval() {
+    let (mut sensor, sim, _) = simulated_sensor();
+    assert!(sensor.set_measure_interval(1).is_err());
+    assert_eq!(2, sim.measure_interval());
+}
+
+#[test]
+fn test_sim_scenario_follows_sample_time() {
+    let (mut sensor, sim, clock) = simulated_sensor();
+    sim.run_scenario(Scenario::new(
+        Profile::constant(400.0).ramp(1000.0, Duration::from_secs(60)),
+        Profile::constant(20.0),
+        Profile::constant(50.0),
+    ));
+    sensor.start().unwrap();
+    clock.advance(Duration::from_secs(31));
+    // the last sample was taken after 30s
+    assert_eq!(700.0, sensor.co2().unwrap());
+}
+
+#[test]
+fn test_sim_fault_nack() {
+    let (mut sensor, sim, _) = simulated_sensor();
+    sim.inject_fault(Fault::Nack, Some(1));
+    match sensor.read_firmware_version() {
+        Err(Error::I2c(_)) => {}
+        other => panic!("unexpected result {:?}", other),
+    }
+    assert!(sensor.read_firmware_version().is_ok());
+}
+
+#[test]
+fn test_sim_fault_corrupt_crc() {
+    let (mut sensor, sim, clock) = simulated_sensor();
+    sensor.start().unwrap();
+    clock.advance(Duration::from_secs(2));
+    sim.inject_fault(Fault::CorruptCrc, Some(1));
+    match sensor.data_available() {
+        Err(Error::CrcError(_)) => {}
+        other => panic!("unexpected result {:?}", other),
+    }
+    assert!(sensor.data_available().unwrap());
+}
+
+#[test]
+fn test_sim_fault_short_read() {
+    let (mut sensor, sim, clock) = simulated_sensor();
+    sensor.start().unwrap();
+    clock.advance(Duration::from_secs(2));
+    sim.inject_fault(Fault::ShortRead(2), Some(1));
+    match sensor.measurement() {
+        Err(Error::NoData(_)) => {}
+        other => panic!("unexpected result {:?}", other),
+    }
+}
+
+#[test]
+fn test_sim_fault_stuck_data_ready() {
+    let (mut sensor, sim, clock) = simulated_sensor();
+    sensor.start().unwrap();
+    sim.inject_fault(Fault::StuckDataReady(false), None);
+    clock.advance(Duration::from_secs(60));
+    assert!(!sensor.data_available().unwrap());
+    assert_eq!(None, sensor.measurement().unwrap());
+    sim.clear_faults();
+    assert!(sensor.data_available().unwrap());
+}
+
+#[test]
+fn test_sim_fault_vanish_scheduled() {
+    let (mut sensor, sim, clock) = simulated_sensor();
+    sim.schedule_fault(Duration::from_secs(10), Fault::Vanish, None);
+    assert!(sensor.read_firmware_version().is_ok());
+    clock.advance(Duration::from_secs(10));
+    assert!(sensor.read_firmware_version().is_err());
+    assert!(sensor.start().is_err());
+    sim.clear_faults();
+    assert!(sensor.read_firmware_version().is_ok());
+}
+
+#[test]
+fn test_sim_stabilizing_after_start() {
+    let (mut sensor, _, clock) = simulated_sensor();
+    let start = clock.now();
+    sensor.start().unwrap();
+    assert_eq!(Some(start), sensor.measuring_since());
+    assert!(sensor.is_stabilizing());
+
+    clock.advance(Duration::from_secs(2));
+    assert!(sensor.measurement().unwrap().unwrap().stabilizing);
+    clock.advance(Duration::from_secs(8));
+    assert!(!sensor.is_stabilizing());
+    assert!(!sensor.measurement().unwrap().unwrap().stabilizing);
+}
+
+#[test]
+fn test_sim_stabilizing_after_configuration_change() {
+    let (mut sensor, _, clock) = simulated_sensor();
+    sensor.set_stabilization_time(Duration::from_secs(20));
+    sensor.start().unwrap();
+    clock.advance(Duration::from_secs(30));
+    assert!(!sensor.is_stabilizing());
+
+    sensor.set_measure_interval(5).unwrap();
+    assert!(sensor.is_stabilizing());
+    clock.advance(Duration::from_secs(20));
+    assert!(!sensor.is_stabilizing());
+
+    // a new pressure restarts the stabilization, but not the measurement
+    let since = sensor.measuring_since();
+    sensor.start_with_alt_comp(980).unwrap();
+    assert!(sensor.is_stabilizing());
+    assert_eq!(since, sensor.measuring_since());
+}
+
+#[test]
+fn test_sim_stabilizing_after_reset_and_stop() {
+    let (mut sensor, _, clock) = simulated_sensor();
+    sensor.start().unwrap();
+    clock.advance(Duration::from_secs(60));
+    sensor.soft_reset().unwrap();
+    assert!(sensor.is_stabilizing());
+    assert_eq!(Some(clock.now()), sensor.measuring_since());
+
+    sensor.stop().unwrap();
+    assert_eq!(None, sensor.measuring_since());
+}
+
+#[test]
+fn test_sim_interval_change_while_stopped_not_stabilizing() {
+    let (mut sensor, _, _) = simulated_sensor();
+    sensor.set_measure_interval(5).unwrap();
+    assert!(!sensor.is_stabilizing());
+}
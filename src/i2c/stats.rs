@@ -0,0 +1,73 @@
+/*
+MIT License
+
+Copyright (c) 2021 Crispin Tschirky <ct@fhr.ch>
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+ */
+
+//! Communication statistics of a sensor.
+//!
+//! Every [`crate::i2c::SCD30`] counts its transactions and their failures, so a slowly degrading
+//! sensor or bus can be told apart from a healthy one. See [`crate::i2c::SCD30::stats`].
+
+use std::time::Instant;
+
+/// Snapshot of the communication counters of a sensor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Stats {
+    /// transactions sent to the sensor including retries
+    pub commands: u64,
+    /// measurements read successfully
+    pub measurements: u64,
+    /// responses with an invalid checksum
+    pub crc_failures: u64,
+    /// responses with fewer bytes than expected
+    pub short_reads: u64,
+    /// transactions failed on the bus
+    pub i2c_errors: u64,
+    /// transactions repeated after a failure
+    pub retries: u64,
+    /// soft resets sent to the sensor
+    pub resets: u64,
+    /// time of the last successful transaction
+    pub last_success: Option<Instant>,
+    /// time of the last failed transaction
+    pub last_error: Option<Instant>,
+}
+
+impl Stats {
+    /// Gets the number of failed transactions
+    pub fn errors(&self) -> u64 {
+        self.crc_failures + self.short_reads + self.i2c_errors
+    }
+
+    /// Gets the share of failed transactions, 0 if nothing was sent yet. Decoding errors other than
+    /// checksum failures are not counted.
+    pub fn error_rate(&self) -> f32 {
+        if self.commands == 0 {
+            0.0
+        } else {
+            self.errors() as f32 / self.commands as f32
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
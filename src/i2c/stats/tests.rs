@@ -0,0 +1,132 @@
+/*
+MIT License
+
+Copyright (c) 2021 Crispin Tschirky <ct@fhr.ch>
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+ */
+
+use crate::clock::Clock;
+use crate::i2c::sim::testing::simulated_sensor;
+use crate::i2c::sim::Fault;
+use crate::i2c::Stats;
+use std::time::Duration;
+
+#[test]
+fn test_stats_count_commands_and_measurements() {
+    let (mut sensor, _, clock) = simulated_sensor();
+    sensor.reset_stats();
+    sensor.start().unwrap();
+    clock.advance(Duration::from_secs(2));
+    assert!(sensor.measurement().unwrap().is_some());
+    sensor.soft_reset().unwrap();
+
+    let stats = sensor.stats();
+    // start, data ready, measurement and reset
+    assert_eq!(4, stats.commands);
+    assert_eq!(1, stats.measurements);
+    assert_eq!(1, stats.resets);
+    assert_eq!(0, stats.errors());
+    assert_eq!(Some(clock.now()), stats.last_success);
+    assert_eq!(None, stats.last_error);
+}
+
+#[test]
+fn test_stats_classify_errors() {
+    let (mut sensor, sim, clock) = simulated_sensor();
+    sensor.reset_stats();
+
+    sim.inject_fault(Fault::CorruptCrc, Some(1));
+    assert!(sensor.read_measure_interval().is_err());
+    sim.inject_fault(Fault::ShortRead(1), Some(1));
+    assert!(sensor.read_measure_interval().is_err());
+    sim.inject_fault(Fault::Nack, Some(1));
+    assert!(sensor.start().is_err());
+    clock.advance(Duration::from_secs(1));
+    assert!(sensor.read_measure_interval().is_ok());
+
+    let stats = sensor.stats();
+    assert_eq!(4, stats.commands);
+    assert_eq!(1, stats.crc_failures);
+    assert_eq!(1, stats.short_reads);
+    assert_eq!(1, stats.i2c_errors);
+    assert_eq!(0.75, stats.error_rate());
+    assert_eq!(Some(clock.now() - Duration::from_secs(1)), stats.last_error);
+    assert_eq!(Some(clock.now()), stats.last_success);
+}
+
+#[test]
+fn test_retries() {
+    let (mut sensor, sim, clock) = simulated_sensor();
+    sensor.reset_stats();
+    sensor.set_retries(2);
+    let start = clock.now();
+
+    sim.inject_fault(Fault::CorruptCrc, Some(2));
+    assert_eq!(2, sensor.read_measure_interval().unwrap());
+    // backoff of 10ms and 20ms
+    assert_eq!(Duration::from_millis(30), clock.now() - start);
+
+    sim.inject_fault(Fault::Nack, Some(3));
+    assert!(sensor.start().is_err());
+
+    let stats = sensor.stats();
+    assert_eq!(6, stats.commands);
+    assert_eq!(4, stats.retries);
+    assert_eq!(2, stats.crc_failures);
+    assert_eq!(3, stats.i2c_errors);
+}
+
+#[test]
+fn test_retry_backoff_capped() {
+    let (mut sensor, sim, clock) = simulated_sensor();
+    sensor.set_retries(10);
+    let start = clock.now();
+
+    sim.inject_fault(Fault::Nack, None);
+    assert!(sensor.read_firmware_version().is_err());
+    // 10ms doubled up to 640ms, then three retries after the 1s cap
+    assert_eq!(Duration::from_millis(1270 + 3000), clock.now() - start);
+    assert_eq!(10, sensor.stats().retries);
+}
+
+#[test]
+fn test_no_retries_by_default() {
+    let (mut sensor, sim, _) = simulated_sensor();
+    sensor.reset_stats();
+    assert_eq!(0, sensor.retries());
+    sim.inject_fault(Fault::Nack, Some(1));
+    assert!(sensor.read_firmware_version().is_err());
+    assert_eq!(0, sensor.stats().retries);
+}
+
+#[test]
+fn test_reset_stats() {
+    let (mut sensor, _, _) = simulated_sensor();
+    sensor.reset_stats();
+    sensor.read_firmware_version().unwrap();
+    assert_ne!(Stats::default(), sensor.stats());
+    sensor.reset_stats();
+    assert_eq!(Stats::default(), sensor.stats());
+}
+
+#[test]
+fn test_error_rate_without_commands() {
+    assert_eq!(0.0, Stats::default().error_rate());
+}
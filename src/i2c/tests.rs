@@ -22,7 +22,15 @@ OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 SOFTWARE.
  */
 
-use crate::i2c::{calculate_crc8, prepare_cmd, prepare_cmd_with_args, prepare_cmd_with_buf};
+use crate::i2c::analyzer::decode_capture;
+use crate::i2c::config::SensorConfig;
+use crate::i2c::record::parse_recording;
+use crate::i2c::sim::testing::simulated_sensor;
+use crate::i2c::sim::Fault;
+use crate::i2c::{
+    calculate_crc8, decode_measure_value_to_u32, decode_measurement, decode_word, prepare_cmd,
+    prepare_cmd_with_args, prepare_cmd_with_buf, Error,
+};
 
 #[test]
 fn test_build_crc() {
@@ -128,3 +136,181 @@ fn test_create_cmd_cmd_only_1() {
     assert_eq!(0x0a, buf[1]);
     assert_eq!(2, buf.len())
 }
+
+#[test]
+fn test_decode_word() {
+    assert_eq!(0xbeef, decode_word(&[0xbe, 0xef, 0x92]).unwrap());
+}
+
+#[test]
+fn test_decode_word_invalid_crc() {
+    match decode_word(&[0xbe, 0xef, 0x93]) {
+        Err(Error::CrcError(_)) => {}
+        other => panic!("unexpected result {:?}", other),
+    }
+}
+
+#[test]
+fn test_decode_word_invalid_length() {
+    match decode_word(&[0xbe, 0xef]) {
+        Err(Error::NoData(_)) => {}
+        other => panic!("unexpected result {:?}", other),
+    }
+}
+
+/// Small xorshift generator, so the property tests are reproducible without extra dependencies.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn bytes(&mut self, max_len: usize) -> Vec<u8> {
+        let len = self.next() as usize % (max_len + 1);
+        (0..len).map(|_| self.next() as u8).collect()
+    }
+
+    fn range(&mut self, min: f32, max: f32) -> f32 {
+        min + (max - min) * (self.next() % 1_000_000) as f32 / 1_000_000.0
+    }
+}
+
+fn encode_value(value: f32) -> Vec<u8> {
+    let b = value.to_bits().to_be_bytes();
+    vec![
+        b[0],
+        b[1],
+        calculate_crc8(&b[0..2]),
+        b[2],
+        b[3],
+        calculate_crc8(&b[2..4]),
+    ]
+}
+
+#[test]
+fn test_decode_measure_value() {
+    assert_eq!(
+        21.5,
+        decode_measure_value_to_u32(&encode_value(21.5)).unwrap()
+    );
+}
+
+#[test]
+fn test_decode_measure_value_short_slice() {
+    match decode_measure_value_to_u32(&[0x41, 0xac]) {
+        Err(Error::NoData(_)) => {}
+        other => panic!("unexpected result {:?}", other),
+    }
+}
+
+#[test]
+fn test_decode_measure_value_rejects_nan_and_infinity() {
+    for value in &[f32::NAN, f32::INFINITY, f32::NEG_INFINITY] {
+        match decode_measure_value_to_u32(&encode_value(*value)) {
+            Err(Error::InvalidData(_)) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+}
+
+#[test]
+fn test_decode_measurement_rejects_impossible_values() {
+    for values in &[
+        [-1.0, 20.0, 50.0],
+        [40_001.0, 20.0, 50.0],
+        [400.0, 120.0, 50.0],
+        [400.0, 20.0, 100.5],
+    ] {
+        let data: Vec<u8> = values.iter().flat_map(|v| encode_value(*v)).collect();
+        match decode_measurement(&data) {
+            Err(Error::InvalidData(_)) => {}
+            other => panic!("unexpected result {:?} for {:?}", other, values),
+        }
+    }
+}
+
+#[test]
+fn test_decode_measurement_round_trip_property() {
+    let mut rng = Rng(0x5cd3_0000_0000_0001);
+    for _ in 0..10_000 {
+        let values = (
+            rng.range(0.0, 40_000.0),
+            rng.range(-40.0, 70.0),
+            rng.range(0.0, 100.0),
+        );
+        let mut data = encode_value(values.0);
+        data.extend(encode_value(values.1));
+        data.extend(encode_value(values.2));
+        assert_eq!(values, decode_measurement(&data).unwrap());
+    }
+}
+
+#[test]
+fn test_decode_measurement_detects_single_bit_errors() {
+    let mut rng = Rng(0x5cd3_0000_0000_0002);
+    let mut data = encode_value(812.0);
+    data.extend(encode_value(21.0));
+    data.extend(encode_value(45.0));
+    for _ in 0..1_000 {
+        let mut corrupted = data.clone();
+        let bit = rng.next() as usize % (corrupted.len() * 8);
+        corrupted[bit / 8] ^= 1 << (bit % 8);
+        match decode_measurement(&corrupted) {
+            Err(Error::CrcError(_)) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+}
+
+#[test]
+fn test_decoders_never_panic_on_arbitrary_input() {
+    let mut rng = Rng(0x5cd3_0000_0000_0003);
+    for _ in 0..20_000 {
+        let data = rng.bytes(40);
+        let _ = decode_word(&data);
+        let _ = decode_measure_value_to_u32(&data);
+        let _ = decode_measurement(&data);
+
+        let text = String::from_utf8_lossy(&data);
+        let _ = decode_capture(&text, 0x61);
+        let _ = parse_recording(&text);
+        let _ = SensorConfig::from_toml(&text);
+    }
+}
+
+#[test]
+fn test_text_decoders_never_panic_on_mutated_input() {
+    let seeds = [
+        "i2c-1: Start\ni2c-1: Address write: 61\ni2c-1: Data write: 03\ni2c-1: Data write: 00\ni2c-1: Stop\ni2c-1: Address read: 61\ni2c-1: Data read: ff\n",
+        "5012 0202 - ok\n5020 0202 0001b0 ok\n7031 0300 - nodata No data read\n",
+        "firmware_version = \"3.66\"\nmeasure_interval = 2\nself_calibration = true\n",
+    ];
+    let mut rng = Rng(0x5cd3_0000_0000_0004);
+    for seed in &seeds {
+        for _ in 0..5_000 {
+            let mut data = seed.as_bytes().to_vec();
+            for _ in 0..(rng.next() % 8) {
+                let pos = rng.next() as usize % data.len();
+                data[pos] = rng.next() as u8;
+            }
+            let text = String::from_utf8_lossy(&data);
+            let _ = decode_capture(&text, 0x61);
+            let _ = parse_recording(&text);
+            let _ = SensorConfig::from_toml(&text);
+        }
+    }
+}
+
+#[test]
+fn test_retries_exhausted_on_persistent_nack() {
+    let (mut sensor, sim, _) = simulated_sensor();
+    sensor.set_retries(u8::MAX);
+    sim.inject_fault(Fault::Nack, None);
+    assert!(sensor.set_measure_interval(5).is_err());
+    assert!(sensor.read_firmware_version().is_err());
+    assert_eq!(2 * u64::from(u8::MAX), sensor.stats().retries);
+}
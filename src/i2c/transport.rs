@@ -0,0 +1,112 @@
+/*
+MIT License
+
+Copyright (c) 2021 Crispin Tschirky <ct@fhr.ch>
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+ */
+
+//! Abstraction of the bus an SCD30 is connected to.
+//!
+//! The driver talks to the sensor through the [`Transport`] trait. It is implemented for a plain
+//! [`rppal::i2c::I2c`] handler with the slave address already set, for multiplexer channels (see
+//! [`crate::i2c::mux`]) and can be implemented for any other bus.
+
+use crate::i2c::Error;
+use rppal::i2c::I2c;
+use std::thread;
+use std::time::Duration;
+
+/// A bus able to exchange commands and responses with a single sensor.
+pub trait Transport {
+    /// Writes the buffer to the sensor.
+    fn write(&mut self, buf: &[u8]) -> Result<(), Error>;
+
+    /// Writes the command buffer, waits for the given delay and reads the response to `out_buf`.
+    /// Returns the number of bytes read. The whole sequence must not be interleaved with other
+    /// transactions on the same bus. A failing read is reported as [`Error::NoData`]. The delay is
+    /// needed by a physical sensor, transports without one may skip it.
+    fn write_read(
+        &mut self,
+        buf: &[u8],
+        delay: Duration,
+        out_buf: &mut [u8],
+    ) -> Result<usize, Error>;
+
+    /// Reads the bus clock speed in Hz if the transport knows it.
+    fn clock_speed(&self) -> Result<u32, Error> {
+        Err(Error::NotImplemented)
+    }
+
+    /// Addresses the slave with the given address in the following transactions. Only needed
+    /// for a bus shared by several slaves, e.g. the one of a [`crate::i2c::mux::Tca9548a`].
+    fn set_slave_address(&mut self, _slave_address: u16) -> Result<(), Error> {
+        Err(Error::NotImplemented)
+    }
+}
+
+impl Transport for I2c {
+    fn write(&mut self, buf: &[u8]) -> Result<(), Error> {
+        I2c::write(self, buf)?;
+        Ok(())
+    }
+
+    fn write_read(
+        &mut self,
+        buf: &[u8],
+        delay: Duration,
+        out_buf: &mut [u8],
+    ) -> Result<usize, Error> {
+        I2c::write(self, buf)?;
+        thread::sleep(delay);
+        I2c::read(self, out_buf).map_err(|_| Error::NoData("No data read".to_string()))
+    }
+
+    fn clock_speed(&self) -> Result<u32, Error> {
+        Ok(I2c::clock_speed(self)?)
+    }
+
+    fn set_slave_address(&mut self, slave_address: u16) -> Result<(), Error> {
+        I2c::set_slave_address(self, slave_address)?;
+        Ok(())
+    }
+}
+
+impl<T: Transport + ?Sized> Transport for Box<T> {
+    fn write(&mut self, buf: &[u8]) -> Result<(), Error> {
+        (**self).write(buf)
+    }
+
+    fn write_read(
+        &mut self,
+        buf: &[u8],
+        delay: Duration,
+        out_buf: &mut [u8],
+    ) -> Result<usize, Error> {
+        (**self).write_read(buf, delay, out_buf)
+    }
+
+    fn clock_speed(&self) -> Result<u32, Error> {
+        (**self).clock_speed()
+    }
+
+    fn set_slave_address(&mut self, slave_address: u16) -> Result<(), Error> {
+        (**self).set_slave_address(slave_address)
+    }
+}
@@ -75,4 +75,8 @@ SOFTWARE.
 //! [RPPAL]: https://crates.io/crates/rppal
 //! [SCD30 Reference]: https://www.sensirion.com/fileadmin/user_upload/customers/sensirion/Dokumente/9.5_CO2/Sensirion_CO2_Sensors_SCD30_Interface_Description.pdf
 //!
+pub mod air_quality;
+pub mod clock;
 pub mod i2c;
+pub mod psychrometrics;
+pub mod ventilation;
@@ -0,0 +1,152 @@
+/*
+MIT License
+
+Copyright (c) 2021 Crispin Tschirky <ct@fhr.ch>
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+ */
+
+//! Psychrometric metrics derived from temperature and relative humidity.
+//!
+//! The vapour pressure follows the Magnus formula with the coefficients of Sonntag (1990) over
+//! water, valid from -45 °C to 60 °C. Without a pressure the standard atmosphere at sea level is
+//! assumed.
+//!
+//! ```
+//! use scd30pi::psychrometrics::dew_point;
+//!
+//! let dew_point = dew_point(20.0, 50.0);
+//! assert!((dew_point - 9.26).abs() < 0.01);
+//! ```
+
+use crate::i2c::compensation::STANDARD_SEA_LEVEL_PRESSURE;
+use crate::i2c::Measurement;
+
+/// Magnus coefficients over water
+const MAGNUS_A: f32 = 6.112;
+const MAGNUS_B: f32 = 17.62;
+const MAGNUS_C: f32 = 243.12;
+
+/// Gets the saturation vapour pressure over water in hPa at the temperature in °C
+pub fn saturation_vapour_pressure(temperature: f32) -> f32 {
+    MAGNUS_A * (MAGNUS_B * temperature / (MAGNUS_C + temperature)).exp()
+}
+
+/// Gets the partial pressure of the water vapour in hPa
+pub fn vapour_pressure(temperature: f32, humidity: f32) -> f32 {
+    humidity / 100.0 * saturation_vapour_pressure(temperature)
+}
+
+/// Gets the dew point in °C, NaN for a humidity of 0
+pub fn dew_point(temperature: f32, humidity: f32) -> f32 {
+    if humidity <= 0.0 {
+        return f32::NAN;
+    }
+    let gamma = (humidity / 100.0).ln() + MAGNUS_B * temperature / (MAGNUS_C + temperature);
+    MAGNUS_C * gamma / (MAGNUS_B - gamma)
+}
+
+/// Gets the absolute humidity in g/m³
+pub fn absolute_humidity(temperature: f32, humidity: f32) -> f32 {
+    // specific gas constant of water vapour 461.5 J/(kg K)
+    vapour_pressure(temperature, humidity) * 100.0 / (461.5 * (temperature + 273.15)) * 1000.0
+}
+
+/// Gets the mixing ratio in g of water per kg of dry air at the pressure in hPa
+pub fn mixing_ratio(temperature: f32, humidity: f32, pressure: f32) -> f32 {
+    let e = vapour_pressure(temperature, humidity);
+    621.97 * e / (pressure - e)
+}
+
+/// Gets the vapour pressure deficit in kPa
+pub fn vapour_pressure_deficit(temperature: f32, humidity: f32) -> f32 {
+    saturation_vapour_pressure(temperature) * (1.0 - humidity / 100.0) / 10.0
+}
+
+/// Gets the heat index in °C with the algorithm of the US National Weather Service: the simple
+/// formula of Steadman below 80 °F, the regression of Rothfusz with its adjustments above.
+pub fn heat_index(temperature: f32, humidity: f32) -> f32 {
+    let t = temperature * 9.0 / 5.0 + 32.0;
+    let rh = humidity;
+    let simple = 0.5 * (t + 61.0 + (t - 68.0) * 1.2 + rh * 0.094);
+    let hi = if (simple + t) / 2.0 < 80.0 {
+        simple
+    } else {
+        let mut hi = -42.379 + 2.049_015_2 * t + 10.143_332 * rh
+            - 0.224_755_4 * t * rh
+            - 0.006_837_83 * t * t
+            - 0.054_817_17 * rh * rh
+            + 0.001_228_74 * t * t * rh
+            + 0.000_852_82 * t * rh * rh
+            - 0.000_001_99 * t * t * rh * rh;
+        if rh < 13.0 && (80.0..=112.0).contains(&t) {
+            hi -= (13.0 - rh) / 4.0 * ((17.0 - (t - 95.0).abs()) / 17.0).sqrt();
+        } else if rh > 85.0 && (80.0..=87.0).contains(&t) {
+            hi += (rh - 85.0) / 10.0 * (87.0 - t) / 5.0;
+        }
+        hi
+    };
+    (hi - 32.0) * 5.0 / 9.0
+}
+
+/// Gets the humidex of Environment Canada in °C
+pub fn humidex(temperature: f32, humidity: f32) -> f32 {
+    temperature + 0.5555 * (vapour_pressure(temperature, humidity) - 10.0)
+}
+
+/// All metrics of a single reading.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Psychrometrics {
+    /// dew point in °C
+    pub dew_point: f32,
+    /// absolute humidity in g/m³
+    pub absolute_humidity: f32,
+    /// mixing ratio in g/kg
+    pub mixing_ratio: f32,
+    /// vapour pressure deficit in kPa
+    pub vapour_pressure_deficit: f32,
+    /// heat index in °C
+    pub heat_index: f32,
+    /// humidex in °C
+    pub humidex: f32,
+}
+
+impl Psychrometrics {
+    /// Computes the metrics from the temperature in °C, the relative humidity in % and the
+    /// pressure in hPa if known.
+    pub fn new(temperature: f32, humidity: f32, pressure: Option<f32>) -> Psychrometrics {
+        let pressure = pressure.unwrap_or(STANDARD_SEA_LEVEL_PRESSURE);
+        Psychrometrics {
+            dew_point: dew_point(temperature, humidity),
+            absolute_humidity: absolute_humidity(temperature, humidity),
+            mixing_ratio: mixing_ratio(temperature, humidity, pressure),
+            vapour_pressure_deficit: vapour_pressure_deficit(temperature, humidity),
+            heat_index: heat_index(temperature, humidity),
+            humidex: humidex(temperature, humidity),
+        }
+    }
+
+    /// Computes the metrics of a measurement with the pressure in hPa if known
+    pub fn from_measurement(measurement: &Measurement, pressure: Option<f32>) -> Psychrometrics {
+        Psychrometrics::new(measurement.temperature, measurement.humidity, pressure)
+    }
+}
+
+#[cfg(test)]
+mod tests;
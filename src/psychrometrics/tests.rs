@@ -0,0 +1,106 @@
+/*
+MIT License
+
+Copyright (c) 2021 Crispin Tschirky <ct@fhr.ch>
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+ */
+
+use crate::i2c::Measurement;
+use crate::psychrometrics::*;
+use std::time::{Duration, Instant};
+
+fn assert_close(expected: f32, actual: f32, tolerance: f32) {
+    assert!(
+        (expected - actual).abs() <= tolerance,
+        "expected {} but got {}",
+        expected,
+        actual
+    );
+}
+
+#[test]
+fn test_saturation_vapour_pressure() {
+    assert_close(6.112, saturation_vapour_pressure(0.0), 0.001);
+    assert_close(23.37, saturation_vapour_pressure(20.0), 0.05);
+    assert_close(42.43, saturation_vapour_pressure(30.0), 0.1);
+}
+
+#[test]
+fn test_dew_point() {
+    assert_close(9.26, dew_point(20.0, 50.0), 0.02);
+    assert_close(25.0, dew_point(25.0, 100.0), 0.001);
+    assert_close(-5.97, dew_point(5.0, 45.0), 0.02);
+    assert!(dew_point(20.0, 0.0).is_nan());
+}
+
+#[test]
+fn test_absolute_humidity() {
+    assert_close(8.64, absolute_humidity(20.0, 50.0), 0.03);
+    assert_close(30.3, absolute_humidity(30.0, 100.0), 0.1);
+    assert_eq!(0.0, absolute_humidity(20.0, 0.0));
+}
+
+#[test]
+fn test_mixing_ratio() {
+    assert_close(7.26, mixing_ratio(20.0, 50.0, 1013.25), 0.02);
+    // less dry air at altitude carries the same vapour
+    assert!(mixing_ratio(20.0, 50.0, 850.0) > mixing_ratio(20.0, 50.0, 1013.25));
+}
+
+#[test]
+fn test_vapour_pressure_deficit() {
+    assert_close(1.169, vapour_pressure_deficit(20.0, 50.0), 0.005);
+    assert_eq!(0.0, vapour_pressure_deficit(25.0, 100.0));
+}
+
+#[test]
+fn test_heat_index() {
+    // NWS table: 90 °F at 70 % is 106 °F, 80 °F at 40 % is 80 °F
+    assert_close(41.1, heat_index(32.22, 70.0), 0.3);
+    assert_close(26.7, heat_index(26.67, 40.0), 0.3);
+    // the simple formula below 80 °F
+    assert_close(20.0, heat_index(20.0, 60.0), 0.5);
+}
+
+#[test]
+fn test_humidex() {
+    // Environment Canada: 30 °C with a dew point of 15 °C is 34
+    let humidity = 100.0 * saturation_vapour_pressure(15.0) / saturation_vapour_pressure(30.0);
+    assert_close(34.0, humidex(30.0, humidity), 0.5);
+}
+
+#[test]
+fn test_from_measurement() {
+    let measurement = Measurement {
+        co2: 600.0,
+        temperature: 20.0,
+        humidity: 50.0,
+        timestamp: Instant::now(),
+        age: Duration::from_secs(0),
+        stabilizing: false,
+    };
+    let metrics = Psychrometrics::from_measurement(&measurement, None);
+    assert_eq!(Psychrometrics::new(20.0, 50.0, Some(1013.25)), metrics);
+    assert_close(9.26, metrics.dew_point, 0.02);
+
+    let at_altitude = Psychrometrics::from_measurement(&measurement, Some(900.0));
+    assert!(at_altitude.mixing_ratio > metrics.mixing_ratio);
+    assert_eq!(metrics.dew_point, at_altitude.dew_point);
+}
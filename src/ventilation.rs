@@ -0,0 +1,313 @@
+/*
+MIT License
+
+Copyright (c) 2021 Crispin Tschirky <ct@fhr.ch>
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+ */
+
+//! Estimation of the ventilation rate from the decay of the CO2 concentration.
+//!
+//! Once a room is empty the excess of the CO2 concentration over outdoor decays exponentially,
+//! `C(t) - C_out = (C(0) - C_out) e^(-λt)`, where λ is the air change rate. A [`DecayAnalyzer`] is
+//! fed with the measurements of a room, detects periods of decay and fits the exponential by a
+//! linear regression of the logarithm of the excess.
+//!
+//! ```no_run
+//! use scd30pi::i2c::SCD30;
+//! use scd30pi::ventilation::DecayAnalyzer;
+//! use std::{thread, time::Duration};
+//!
+//! let mut sensor = SCD30::new().unwrap();
+//! let mut analyzer = DecayAnalyzer::new(420.0);
+//! sensor.start().unwrap();
+//! loop {
+//!     if let Some(measurement) = sensor.measurement().unwrap() {
+//!         if let Some(estimate) = analyzer.push(&measurement) {
+//!             println!(
+//!                 "{:.1} air changes per hour ({:?})",
+//!                 estimate.air_changes_per_hour, estimate.confidence
+//!             );
+//!         }
+//!     }
+//!     thread::sleep(Duration::from_secs(60));
+//! }
+//! ```
+
+use crate::i2c::Measurement;
+use std::time::{Duration, Instant};
+
+/// How far an estimate can be trusted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Confidence {
+    Low,
+    Medium,
+    High,
+}
+
+/// Air change rate estimated from a single decay period.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DecayEstimate {
+    /// time of the first sample of the decay
+    pub start: Instant,
+    /// time of the last sample of the decay
+    pub end: Instant,
+    /// CO2 concentration in ppm at the start
+    pub start_co2: f32,
+    /// CO2 concentration in ppm at the end
+    pub end_co2: f32,
+    /// number of samples fitted
+    pub samples: usize,
+    /// air changes per hour, the decay constant λ
+    pub air_changes_per_hour: f32,
+    /// standard error of the air changes per hour
+    pub std_error: f32,
+    /// coefficient of determination of the fit, 1 for a perfect exponential
+    pub r_squared: f32,
+    /// rating of the fit quality and the length of the decay
+    pub confidence: Confidence,
+}
+
+impl DecayEstimate {
+    /// Gets the duration of the decay
+    pub fn duration(&self) -> Duration {
+        self.end - self.start
+    }
+}
+
+/// Detects decay periods in a stream of measurements and estimates the air change rate.
+#[derive(Debug, Clone)]
+pub struct DecayAnalyzer {
+    /// outdoor CO2 concentration in ppm
+    outdoor: f32,
+    /// shortest decay evaluated
+    min_duration: Duration,
+    /// smallest drop in ppm of a decay evaluated
+    min_drop: f32,
+    /// band in ppm of the measurement noise, a rise above the lowest value of the decay by more
+    /// ends it and a drop from the peak by more starts it
+    noise: f32,
+    /// excess over outdoor in ppm below which the decay ends, the logarithm gets too noisy
+    min_excess: f32,
+    /// longest time between two samples of a decay
+    max_gap: Duration,
+    /// samples of the current decay
+    samples: Vec<(Instant, f32)>,
+}
+
+impl DecayAnalyzer {
+    /// Creates an analyzer for the given outdoor CO2 concentration in ppm. Decays of at least 15
+    /// minutes and 150 ppm are evaluated.
+    pub fn new(outdoor: f32) -> DecayAnalyzer {
+        DecayAnalyzer {
+            outdoor,
+            min_duration: Duration::from_secs(15 * 60),
+            min_drop: 150.0,
+            noise: 15.0,
+            min_excess: 50.0,
+            max_gap: Duration::from_secs(10 * 60),
+            samples: Vec::new(),
+        }
+    }
+
+    /// Sets the shortest decay evaluated
+    pub fn set_min_duration(&mut self, min_duration: Duration) {
+        self.min_duration = min_duration;
+    }
+
+    /// Sets the smallest drop in ppm of a decay evaluated
+    pub fn set_min_drop(&mut self, min_drop: f32) {
+        self.min_drop = min_drop;
+    }
+
+    /// Sets the band in ppm of the measurement noise. A rise above the lowest value of a decay
+    /// by more ends it, a drop from the peak by more starts it. A negative band is set to 0.
+    pub fn set_noise(&mut self, noise: f32) {
+        self.noise = noise.max(0.0);
+    }
+
+    /// Sets the excess over outdoor in ppm below which a decay ends. As the logarithm of the
+    /// excess is fitted it is at least 1 ppm.
+    pub fn set_min_excess(&mut self, min_excess: f32) {
+        self.min_excess = min_excess.max(1.0);
+    }
+
+    /// Sets the longest time between two samples of a decay
+    pub fn set_max_gap(&mut self, max_gap: Duration) {
+        self.max_gap = max_gap;
+    }
+
+    /// Adds a measurement. Returns the estimate of a decay which ended with this measurement.
+    /// Measurements flagged as stabilizing are ignored.
+    pub fn push(&mut self, measurement: &Measurement) -> Option<DecayEstimate> {
+        if measurement.stabilizing {
+            return None;
+        }
+        let (timestamp, co2) = (measurement.timestamp, measurement.co2);
+        let continues = match self.samples.last() {
+            Some((last, _)) if timestamp <= *last => return None,
+            Some((last, _)) => {
+                let lowest = self
+                    .samples
+                    .iter()
+                    .map(|(_, co2)| *co2)
+                    .fold(f32::INFINITY, f32::min);
+                timestamp - *last <= self.max_gap
+                    && co2 <= lowest + self.noise
+                    && co2 - self.outdoor >= self.min_excess
+            }
+            None => false,
+        };
+        if continues {
+            self.samples.push((timestamp, co2));
+            return None;
+        }
+        let estimate = self.finish();
+        if co2 - self.outdoor >= self.min_excess {
+            self.samples.push((timestamp, co2));
+        }
+        estimate
+    }
+
+    /// Ends the current decay and gets its estimate if it qualifies
+    pub fn finish(&mut self) -> Option<DecayEstimate> {
+        let samples = std::mem::take(&mut self.samples);
+        // a decay starts where the concentration leaves the plateau around the peak
+        let (peak, peak_co2) = samples.iter().enumerate().fold(
+            (0, f32::NEG_INFINITY),
+            |(peak, peak_co2), (i, (_, co2))| {
+                if *co2 > peak_co2 {
+                    (i, *co2)
+                } else {
+                    (peak, peak_co2)
+                }
+            },
+        );
+        let first_drop = peak
+            + samples[peak..]
+                .iter()
+                .position(|(_, co2)| *co2 < peak_co2 - self.noise)?;
+        let samples = &samples[first_drop.saturating_sub(1)..];
+        let (start, start_co2) = *samples.first()?;
+        let (end, end_co2) = *samples.last()?;
+        if end - start < self.min_duration || start_co2 - end_co2 < self.min_drop {
+            return None;
+        }
+        self.fit(samples)
+    }
+
+    /// Fits the exponential to the samples
+    fn fit(&self, samples: &[(Instant, f32)]) -> Option<DecayEstimate> {
+        let (start, start_co2) = samples[0];
+        let (end, end_co2) = samples[samples.len() - 1];
+        let points: Vec<(f64, f64)> = samples
+            .iter()
+            .map(|(time, co2)| {
+                (
+                    (*time - start).as_secs_f64() / 3600.0,
+                    f64::from(co2 - self.outdoor).ln(),
+                )
+            })
+            .collect();
+        let fit = linear_regression(&points)?;
+        let air_changes_per_hour = -fit.slope as f32;
+        if air_changes_per_hour <= 0.0 {
+            return None;
+        }
+        let r_squared = fit.r_squared as f32;
+        let duration = end - start;
+        let confidence = if r_squared >= 0.95
+            && points.len() >= 10
+            && duration >= Duration::from_secs(30 * 60)
+        {
+            Confidence::High
+        } else if r_squared >= 0.8 && points.len() >= 5 {
+            Confidence::Medium
+        } else {
+            Confidence::Low
+        };
+        Some(DecayEstimate {
+            start,
+            end,
+            start_co2,
+            end_co2,
+            samples: points.len(),
+            air_changes_per_hour,
+            std_error: fit.slope_std_error as f32,
+            r_squared,
+            confidence,
+        })
+    }
+}
+
+/// Estimates the air change rate of all decays in the measurements, oldest first
+pub fn estimate(measurements: &[Measurement], outdoor: f32) -> Vec<DecayEstimate> {
+    let mut analyzer = DecayAnalyzer::new(outdoor);
+    let mut estimates: Vec<DecayEstimate> = measurements
+        .iter()
+        .filter_map(|measurement| analyzer.push(measurement))
+        .collect();
+    estimates.extend(analyzer.finish());
+    estimates
+}
+
+/// Result of a least squares fit of a line
+struct LinearFit {
+    slope: f64,
+    slope_std_error: f64,
+    r_squared: f64,
+}
+
+/// Fits a line to the points, `None` for less than three points or a single x value
+fn linear_regression(points: &[(f64, f64)]) -> Option<LinearFit> {
+    let n = points.len() as f64;
+    if points.len() < 3 {
+        return None;
+    }
+    let mean_x = points.iter().map(|(x, _)| x).sum::<f64>() / n;
+    let mean_y = points.iter().map(|(_, y)| y).sum::<f64>() / n;
+    let sxx: f64 = points.iter().map(|(x, _)| (x - mean_x).powi(2)).sum();
+    let sxy: f64 = points
+        .iter()
+        .map(|(x, y)| (x - mean_x) * (y - mean_y))
+        .sum();
+    let syy: f64 = points.iter().map(|(_, y)| (y - mean_y).powi(2)).sum();
+    if sxx == 0.0 {
+        return None;
+    }
+    let slope = sxy / sxx;
+    let intercept = mean_y - slope * mean_x;
+    let residuals: f64 = points
+        .iter()
+        .map(|(x, y)| (y - intercept - slope * x).powi(2))
+        .sum();
+    let r_squared = if syy == 0.0 {
+        1.0
+    } else {
+        1.0 - residuals / syy
+    };
+    Some(LinearFit {
+        slope,
+        slope_std_error: (residuals / (n - 2.0) / sxx).sqrt(),
+        r_squared,
+    })
+}
+
+#[cfg(test)]
+mod tests;
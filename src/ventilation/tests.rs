@@ -0,0 +1,241 @@
+/*
+MIT License
+
+Copyright (c) 2021 Crispin Tschirky <ct@fhr.ch>
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+ */
+
+use crate::clock::Clock;
+use crate::i2c::sim::testing::simulated_sensor;
+use crate::i2c::sim::Scenario;
+use crate::i2c::Measurement;
+use crate::ventilation::{estimate, Confidence, DecayAnalyzer};
+use std::time::{Duration, Instant};
+
+fn measurement(start: Instant, minute: u64, co2: f32) -> Measurement {
+    Measurement {
+        co2,
+        temperature: 21.0,
+        humidity: 45.0,
+        timestamp: start + Duration::from_secs(minute * 60),
+        age: Duration::from_secs(0),
+        stabilizing: false,
+    }
+}
+
+/// Samples a decay from `peak` towards 400 ppm every minute
+fn decay(start: Instant, peak: f32, air_changes_per_hour: f32, minutes: u64) -> Vec<Measurement> {
+    (0..minutes)
+        .map(|minute| {
+            let co2 = 400.0 + (peak - 400.0) * (-air_changes_per_hour * minute as f32 / 60.0).exp();
+            measurement(start, minute, co2)
+        })
+        .collect()
+}
+
+#[test]
+fn test_exact_decay() {
+    let start = Instant::now();
+    let estimates = estimate(&decay(start, 1400.0, 2.0, 60), 400.0);
+    assert_eq!(1, estimates.len());
+    let estimate = estimates[0];
+    assert!((estimate.air_changes_per_hour - 2.0).abs() < 0.001);
+    assert!(estimate.r_squared > 0.999);
+    assert!(estimate.std_error < 0.001);
+    assert_eq!(Confidence::High, estimate.confidence);
+    assert_eq!(start, estimate.start);
+    assert_eq!(1400.0, estimate.start_co2);
+    assert_eq!(60, estimate.samples);
+    assert_eq!(Duration::from_secs(59 * 60), estimate.duration());
+}
+
+#[test]
+fn test_decay_ends_near_outdoor() {
+    let start = Instant::now();
+    let mut analyzer = DecayAnalyzer::new(400.0);
+    let mut estimates = Vec::new();
+    for m in decay(start, 1400.0, 2.0, 180) {
+        estimates.extend(analyzer.push(&m));
+    }
+    // the excess drops below 50 ppm after 90 minutes
+    assert_eq!(1, estimates.len());
+    assert_eq!(Duration::from_secs(89 * 60), estimates[0].duration());
+    assert_eq!(None, analyzer.finish());
+}
+
+#[test]
+fn test_noisy_decay() {
+    let start = Instant::now();
+    let mut measurements = decay(start, 1400.0, 3.0, 40);
+    for (i, m) in measurements.iter_mut().enumerate() {
+        m.co2 += [12.0, -12.0, 6.0, -6.0][i % 4];
+    }
+    let estimates = estimate(&measurements, 400.0);
+    assert_eq!(1, estimates.len());
+    let estimate = estimates[0];
+    assert!((estimate.air_changes_per_hour - 3.0).abs() < 0.3);
+    assert!(estimate.r_squared < 0.999);
+    assert!(estimate.std_error > 0.001);
+}
+
+#[test]
+fn test_confidence() {
+    let start = Instant::now();
+    // exact but short
+    let mut analyzer = DecayAnalyzer::new(400.0);
+    analyzer.set_min_duration(Duration::from_secs(5 * 60));
+    let mut estimates: Vec<_> = decay(start, 1400.0, 6.0, 10)
+        .iter()
+        .filter_map(|m| analyzer.push(m))
+        .collect();
+    estimates.extend(analyzer.finish());
+    assert_eq!(Confidence::Medium, estimates[0].confidence);
+
+    // a plateau followed by a drop is no exponential
+    let mut measurements: Vec<_> = (0..20).map(|i| measurement(start, i, 1200.0)).collect();
+    measurements.extend((20..40).map(|i| measurement(start, i, 900.0 - i as f32)));
+    let estimates = estimate(&measurements, 400.0);
+    assert_eq!(Confidence::Low, estimates[0].confidence);
+}
+
+#[test]
+fn test_no_decay() {
+    let start = Instant::now();
+    let rising: Vec<_> = (0..60)
+        .map(|i| measurement(start, i, 400.0 + 20.0 * i as f32))
+        .collect();
+    assert!(estimate(&rising, 400.0).is_empty());
+    // too short
+    assert!(estimate(&decay(start, 1400.0, 2.0, 10), 400.0).is_empty());
+    // too small
+    assert!(estimate(&decay(start, 600.0, 1.0, 60), 400.0).is_empty());
+}
+
+#[test]
+fn test_rise_and_gap_split_decays() {
+    let start = Instant::now();
+    let mut measurements = decay(start, 1400.0, 2.0, 30);
+    // occupied again
+    measurements.extend(
+        decay(start, 1500.0, 1.0, 40)
+            .into_iter()
+            .map(|m| Measurement {
+                timestamp: m.timestamp + Duration::from_secs(30 * 60),
+                ..m
+            }),
+    );
+    // the sensor was offline for an hour
+    measurements.extend(
+        decay(start, 1100.0, 2.0, 20)
+            .into_iter()
+            .map(|m| Measurement {
+                timestamp: m.timestamp + Duration::from_secs(130 * 60),
+                ..m
+            }),
+    );
+    let estimates = estimate(&measurements, 400.0);
+    assert_eq!(3, estimates.len());
+    assert!((estimates[0].air_changes_per_hour - 2.0).abs() < 0.001);
+    assert!((estimates[1].air_changes_per_hour - 1.0).abs() < 0.001);
+    assert_eq!(1500.0, estimates[1].start_co2);
+    assert_eq!(start + Duration::from_secs(130 * 60), estimates[2].start);
+}
+
+#[test]
+fn test_stabilizing_samples_ignored() {
+    let start = Instant::now();
+    let mut measurements = decay(start, 1400.0, 2.0, 60);
+    measurements[10].stabilizing = true;
+    measurements[10].co2 = 2000.0;
+    let estimates = estimate(&measurements, 400.0);
+    assert_eq!(1, estimates.len());
+    assert_eq!(59, estimates[0].samples);
+}
+
+#[test]
+fn test_negative_noise() {
+    let start = Instant::now();
+    let mut analyzer = DecayAnalyzer::new(400.0);
+    analyzer.set_noise(-10.0);
+    let mut estimates: Vec<_> = decay(start, 1400.0, 2.0, 60)
+        .iter()
+        .filter_map(|m| analyzer.push(m))
+        .collect();
+    estimates.extend(analyzer.finish());
+    assert_eq!(1, estimates.len());
+    assert!((estimates[0].air_changes_per_hour - 2.0).abs() < 0.001);
+}
+
+#[test]
+fn test_noise_lowered_during_decay() {
+    let start = Instant::now();
+    let mut analyzer = DecayAnalyzer::new(400.0);
+    assert_eq!(None, analyzer.push(&measurement(start, 0, 1000.0)));
+    assert_eq!(None, analyzer.push(&measurement(start, 1, 1010.0)));
+    analyzer.set_noise(5.0);
+    assert_eq!(None, analyzer.finish());
+}
+
+#[test]
+fn test_min_excess_not_positive() {
+    let start = Instant::now();
+    for min_excess in [0.0, -50.0] {
+        let mut analyzer = DecayAnalyzer::new(400.0);
+        analyzer.set_min_excess(min_excess);
+        // down to and below outdoor
+        let mut estimates: Vec<_> = (0..60)
+            .map(|i| measurement(start, i, 1400.0 - 20.0 * i as f32))
+            .filter_map(|m| analyzer.push(&m))
+            .collect();
+        estimates.extend(analyzer.finish());
+        assert_eq!(1, estimates.len());
+        assert!(estimates[0].air_changes_per_hour.is_finite());
+        assert!(estimates[0].r_squared.is_finite());
+        assert!(estimates[0].end_co2 > 400.0);
+    }
+}
+
+#[test]
+fn test_simulated_window_opening() {
+    let (mut sensor, sim, clock) = simulated_sensor();
+    sim.run_scenario(Scenario::window_opening(
+        1400.0,
+        420.0,
+        10.0,
+        Duration::from_secs(20 * 60),
+        Duration::from_secs(30 * 60),
+    ));
+    sensor.set_measure_interval(60).unwrap();
+    sensor.start().unwrap();
+
+    let mut analyzer = DecayAnalyzer::new(420.0);
+    let mut estimates = Vec::new();
+    let end = clock.now() + Duration::from_secs(4 * 3600);
+    while clock.now() < end {
+        clock.advance(Duration::from_secs(60));
+        if let Some(m) = sensor.measurement().unwrap() {
+            estimates.extend(analyzer.push(&m));
+        }
+    }
+    estimates.extend(analyzer.finish());
+    assert_eq!(1, estimates.len());
+    assert!((estimates[0].air_changes_per_hour - 2.0).abs() < 0.05);
+    assert_eq!(Confidence::High, estimates[0].confidence);
+}